@@ -0,0 +1,85 @@
+use crate::{Coordinate, EngineInfo, EngineSchematic, PartId};
+
+// index of a symbol in `Analysis::symbols`
+pub type SymbolId = usize;
+
+// a number in the schematic and the symbols around it
+#[derive(Debug)]
+pub struct NumberReport {
+    pub value: u64,
+    pub coord: Coordinate,
+    pub len: usize,
+    pub symbols: Vec<SymbolId>,
+}
+
+impl NumberReport {
+    // no symbol touches it, so it is not a part
+    pub fn is_orphan(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+// a symbol in the schematic and the parts around it
+#[derive(Debug)]
+pub struct SymbolReport {
+    pub coord: Coordinate,
+    pub symbol: u8,
+    pub gear: bool,
+    pub parts: Vec<PartId>,
+}
+
+// who touches who, for every number and symbol in the schematic
+#[derive(Debug)]
+pub struct Analysis {
+    // indexed by `PartId`
+    pub numbers: Vec<NumberReport>,
+    // in reading order
+    pub symbols: Vec<SymbolReport>,
+}
+
+impl Analysis {
+    pub fn orphans(&self) -> impl Iterator<Item = &NumberReport> {
+        self.numbers.iter().filter(|n| n.is_orphan())
+    }
+}
+
+impl EngineSchematic {
+    pub fn analyse(&self) -> Analysis {
+        let mut numbers: Vec<NumberReport> = self.parts
+            .iter()
+            .map(|part| match part {
+                EngineInfo::PartNumber(value, coord, len) => NumberReport {
+                    value: *value,
+                    coord: *coord,
+                    len: *len,
+                    symbols: Vec::new(),
+                },
+                _ => unreachable!("only part numbers are in parts"),
+            })
+            .collect();
+
+        let mut symbols = Vec::new();
+        for row in &self.grid {
+            for info in row {
+                let (coord, symbol) = match info {
+                    EngineInfo::Symbol(c, s) | EngineInfo::Gear(c, s, _) => (*c, *s),
+                    EngineInfo::PartNumber(_, _, _) => continue,
+                };
+                let mut parts = Vec::with_capacity(8);
+                self.search_adjacents(coord, &mut parts);
+                // link the parts back to this symbol
+                for id in &parts {
+                    numbers[*id].symbols.push(symbols.len());
+                }
+                symbols.push(SymbolReport {
+                    coord,
+                    symbol,
+                    gear: info.is_gear(),
+                    parts,
+                });
+            }
+        }
+
+        Analysis { numbers, symbols }
+    }
+}
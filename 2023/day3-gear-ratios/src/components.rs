@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+
+use crate::{Coordinate, EngineInfo, EngineSchematic, PartId};
+
+// index of a component in `Components::list`
+pub type ComponentId = usize;
+
+// colours handed out to components in turn
+const PALETTE: [&str; 6] = [
+    "\x1b[1;31m", // red
+    "\x1b[1;32m", // green
+    "\x1b[1;33m", // yellow
+    "\x1b[1;34m", // blue
+    "\x1b[1;35m", // magenta
+    "\x1b[1;36m", // cyan
+];
+
+// a "machine": numbers and symbols that are all linked by touching
+#[derive(Debug, Default)]
+pub struct Component {
+    pub parts: Vec<PartId>,
+    pub symbols: Vec<Coordinate>,
+    pub gears: Vec<Coordinate>,
+    // only numbers next to a symbol count as parts
+    pub part_sum: u64,
+    // ratios of the gears that follow the gear rule
+    pub gear_ratios: u64,
+}
+
+impl Component {
+    // numbers plus symbols (gears are symbols too)
+    pub fn size(&self) -> usize {
+        self.parts.len() + self.symbols.len() + self.gears.len()
+    }
+}
+
+pub struct Components {
+    // in reading order of the first item of each component
+    pub list: Vec<Component>,
+    // component of every non empty cell
+    labels: Vec<Vec<Option<ComponentId>>>,
+}
+
+impl Components {
+    pub fn at(&self, coord: Coordinate) -> Option<ComponentId> {
+        *self.labels.get(coord.row())?.get(coord.col())?
+    }
+}
+
+// union find over items, with path halving
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+impl EngineSchematic {
+    // label every group of items that touch each other (all 8 neighbours),
+    // numbers next to numbers and symbols next to symbols count as well
+    pub fn components(&self) -> Components {
+        // give every item a node and mark the cells it covers
+        let mut items: Vec<&EngineInfo> = Vec::new();
+        let mut cells: Vec<Vec<Option<usize>>> = self.index
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect();
+        for (num, row) in self.grid.iter().enumerate() {
+            for info in row {
+                cells[num][info.span()].fill(Some(items.len()));
+                items.push(info);
+            }
+        }
+
+        // join each item with everything around it
+        let mut parent: Vec<usize> = (0..items.len()).collect();
+        for (num, row) in cells.iter().enumerate() {
+            for (col, node) in row.iter().enumerate() {
+                let Some(node) = node else { continue };
+                for n in Coordinate::new(col, num).neighbours() {
+                    let other = cells.get(n.row()).and_then(|r| r.get(n.col()));
+                    if let Some(Some(other)) = other {
+                        let (a, b) = (find(&mut parent, *node), find(&mut parent, *other));
+                        parent[a] = b;
+                    }
+                }
+            }
+        }
+
+        // number the roots in reading order and fill the components in
+        let touched = self.touched_parts();
+        let mut around = Vec::with_capacity(8);
+        let mut root_label: Vec<Option<ComponentId>> = vec![None; items.len()];
+        let mut node_label: Vec<ComponentId> = Vec::with_capacity(items.len());
+        let mut list: Vec<Component> = Vec::new();
+        for (node, info) in items.iter().enumerate() {
+            let root = find(&mut parent, node);
+            let label = *root_label[root].get_or_insert_with(|| {
+                list.push(Component::default());
+                list.len() - 1
+            });
+            node_label.push(label);
+            let comp = &mut list[label];
+            match info {
+                EngineInfo::PartNumber(value, coord, _) => {
+                    let id = self.part_at(*coord).expect("part is indexed");
+                    comp.parts.push(id);
+                    if touched[id] {
+                        comp.part_sum += value;
+                    }
+                }
+                EngineInfo::Symbol(coord, _) => comp.symbols.push(*coord),
+                EngineInfo::Gear(coord, _, _) => {
+                    comp.gears.push(*coord);
+                    self.search_adjacents(*coord, &mut around);
+                    if self.rule.adjacency.accepts(around.len()) {
+                        comp.gear_ratios += self.rule.combine.apply(around
+                            .iter()
+                            .map(|id| self.parts[*id].value())
+                        );
+                    }
+                    around.clear();
+                }
+            }
+        }
+
+        let labels = cells
+            .into_iter()
+            .map(|row| row.into_iter().map(|n| n.map(|n| node_label[n])).collect())
+            .collect();
+        Components { list, labels }
+    }
+
+    // draw the schematic with every component in its own colour
+    pub fn render_components(&self, comps: &Components, out: &mut impl Write, color: bool) -> io::Result<()> {
+        self.draw(out, color, |info| {
+            let label = comps.at(info.coord()).expect("item has a component");
+            PALETTE[label % PALETTE.len()]
+        })
+    }
+}
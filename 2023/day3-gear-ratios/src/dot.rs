@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use crate::analysis::Analysis;
+
+// symbols can be anything, keep the dot labels valid
+fn escape(sym: u8) -> String {
+    match sym {
+        b'"' => "\\\"".to_string(),
+        b'\\' => "\\\\".to_string(),
+        _ => (sym as char).to_string(),
+    }
+}
+
+impl Analysis {
+    // write the part <-> symbol graph in graphviz dot format
+    // - numbers are boxes, labelled with their value and (row,col)
+    // - symbols are circles, gears are filled in
+    // - an edge means the two are next to each other
+    pub fn write_dot(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "graph schematic {{")?;
+        for (id, num) in self.numbers.iter().enumerate() {
+            writeln!(
+                out,
+                "    p{} [shape=box, label=\"{} ({},{})\"{}];",
+                id, num.value, num.coord.row(), num.coord.col(),
+                if num.is_orphan() { ", style=dashed" } else { "" }
+            )?;
+        }
+        for (id, sym) in self.symbols.iter().enumerate() {
+            writeln!(
+                out,
+                "    s{} [shape=circle, label=\"{} ({},{})\"{}];",
+                id, escape(sym.symbol), sym.coord.row(), sym.coord.col(),
+                if sym.gear { ", style=filled, fillcolor=gold" } else { "" }
+            )?;
+        }
+        for (id, sym) in self.symbols.iter().enumerate() {
+            for part in &sym.parts {
+                writeln!(out, "    s{} -- p{};", id, part)?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
@@ -1,6 +1,17 @@
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
+use std::ops::Range;
+use std::str::FromStr;
+
+use clap::{Parser, ValueEnum};
+
+mod analysis;
+mod components;
+mod dot;
+mod render;
+mod stream;
+
+use stream::StreamSolver;
 
 #[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
 struct Coordinate {
@@ -8,6 +19,22 @@ struct Coordinate {
     y: usize,
 }
 
+// search around radius of a coordinate
+// (row, col)
+const RADIUS: [(isize, isize); 8] = [
+    // upper row: ul, up, ur
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    // left and right
+    (0, -1),
+    (0, 1),
+    // bottom row: bl, b, br
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
 impl Coordinate {
     fn new(x:usize, y:usize) -> Self {
         Self { x, y,}
@@ -20,61 +47,161 @@ impl Coordinate {
     fn col(&self) -> usize {
         self.x
     }
+
+    // the 8 cells around us, skipping the ones that fall off the
+    // top or left of the grid (bottom/right are checked on lookup)
+    fn neighbours(&self) -> impl Iterator<Item = Coordinate> + '_ {
+        RADIUS.iter().filter_map(|(row, col)| {
+            let sr = self.row().checked_add_signed(*row)?;
+            let sc = self.col().checked_add_signed(*col)?;
+            Some(Coordinate::new(sc, sr))
+        })
+    }
 }
 
-#[derive(Debug)]
+// index of a part number in `EngineSchematic::parts`
+type PartId = usize;
+
+#[derive(Clone, Copy, Debug)]
 enum EngineInfo {
     // value, coord (start), length
     PartNumber(u64, Coordinate, usize),
-    Symbol(Coordinate),
-    Gear(Coordinate, u64),
+    // coord, symbol
+    Symbol(Coordinate, u8),
+    // coord, symbol, ratio
+    Gear(Coordinate, u8, u64),
 }
 
 impl EngineInfo {
     fn is_symbol(&self) -> bool {
-        match self {
-            EngineInfo::Symbol(_) | EngineInfo::Gear(_, _) => true,
-            _ => false,
-        }
+        matches!(self, EngineInfo::Symbol(_, _) | EngineInfo::Gear(_, _, _))
     }
 
     fn is_gear(&self) -> bool {
-        match self {
-            EngineInfo::Gear(_, _) => true,
-            _ => false,
-        }
+        matches!(self, EngineInfo::Gear(_, _, _))
     }
 
     fn coord(&self) -> Coordinate {
         match self {
             EngineInfo::PartNumber(_, c, _) => *c,
-            EngineInfo::Symbol(c) => *c,
-            EngineInfo::Gear(c, _) => *c,
+            EngineInfo::Symbol(c, _) => *c,
+            EngineInfo::Gear(c, _, _) => *c,
         }
     }
 
     fn value(&self) -> u64 {
         match self {
             EngineInfo::PartNumber(v, _, _) => *v,
-            EngineInfo::Gear(_, ratio) => *ratio,
+            EngineInfo::Gear(_, _, ratio) => *ratio,
             _ => todo!()
         }
     }
 
     // set_ratio??
 
-    fn in_range(&self, col: usize) -> bool {
+    // the columns this item covers
+    fn span(&self) -> Range<usize> {
+        match self {
+            EngineInfo::PartNumber(_, c, len) => c.col()..(c.col()+len),
+            EngineInfo::Symbol(c, _) => c.col()..(c.col()+1),
+            EngineInfo::Gear(c, _, _) => c.col()..(c.col()+1),
+        }
+    }
+}
+
+// how many parts a gear needs to be touching
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Adjacency {
+    Exact(usize),
+    Min(usize),
+    Max(usize),
+}
+
+impl Adjacency {
+    fn accepts(&self, parts: usize) -> bool {
+        match self {
+            Adjacency::Exact(n) => parts == *n,
+            Adjacency::Min(n) => parts >= *n,
+            Adjacency::Max(n) => parts <= *n,
+        }
+    }
+}
+
+impl FromStr for Adjacency {
+    type Err = String;
+
+    // exact:N, min:N or max:N
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, n) = s
+            .split_once(':')
+            .ok_or(format!("expected exact:N, min:N or max:N, got '{}'", s))?;
+        let n: usize = n
+            .trim()
+            .parse()
+            .map_err(|e| format!("bad part count '{}': {}", n, e))?;
+        match kind.trim() {
+            "exact" => Ok(Adjacency::Exact(n)),
+            "min" => Ok(Adjacency::Min(n)),
+            "max" => Ok(Adjacency::Max(n)),
+            _ => Err(format!("unknown adjacency '{}'", kind)),
+        }
+    }
+}
+
+// how the parts around a gear make up its ratio
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Combine {
+    fn apply(&self, parts: impl Iterator<Item = u64>) -> u64 {
         match self {
-            EngineInfo::PartNumber(_, c, len) => (c.col()..(c.col()+len)).contains(&col),
-            EngineInfo::Symbol(c) => c.col() == col,
-            EngineInfo::Gear(c, _) => c.col() == col,
+            Combine::Product => parts.product(),
+            Combine::Sum => parts.sum(),
+            Combine::Max => parts.max().unwrap_or(0),
         }
     }
 }
 
+// what counts as a gear and how its ratio is worked out
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GearRule {
+    symbols: Vec<u8>,
+    adjacency: Adjacency,
+    combine: Combine,
+}
+
+impl Default for GearRule {
+    // the puzzle rules: a '*' next to exactly two parts, multiplied
+    fn default() -> Self {
+        Self {
+            symbols: vec![b'*'],
+            adjacency: Adjacency::Exact(2),
+            combine: Combine::Product,
+        }
+    }
+}
+
+impl GearRule {
+    fn is_gear(&self, symbol: u8) -> bool {
+        self.symbols.contains(&symbol)
+    }
+}
+
 struct EngineSchematic {
     input: BufReader<File>,
-    grid: Vec<Vec<EngineInfo>>
+    // sparse list of numbers and symbols per row
+    grid: Vec<Vec<EngineInfo>>,
+    // every part number we found, a `PartId` indexes into this
+    parts: Vec<EngineInfo>,
+    // dense map of the schematic, each cell covered by a number
+    // points at its part so neighbour lookups are O(1)
+    index: Vec<Vec<Option<PartId>>>,
+    // decides which symbols are gears, set before parsing
+    rule: GearRule,
 }
 
 impl EngineSchematic {
@@ -84,9 +211,16 @@ impl EngineSchematic {
         Self {
             input: reader,
             grid: Vec::new(),
+            parts: Vec::new(),
+            index: Vec::new(),
+            rule: GearRule::default(),
         }
     }
 
+    fn set_gear_rule(&mut self, rule: GearRule) {
+        self.rule = rule;
+    }
+
     fn parse(&mut self) {
         // iterate over all bytes in the stream
         // save locations of numbers and symbols (sparse matrix?)
@@ -100,10 +234,11 @@ impl EngineSchematic {
         while self.input.read_line(&mut string).unwrap() > 0 {
             // print!("{}", string);
             let mut info_list = Vec::new();
+            let mut cells = Vec::with_capacity(string.len());
             for b in string.as_bytes() {
                 if b.is_ascii_digit() {
                     // record the start of the number
-                    if num_str.len() == 0 {
+                    if num_str.is_empty() {
                         num_coor.x = col;
                         num_coor.y = row;
                     }
@@ -111,25 +246,25 @@ impl EngineSchematic {
                     num_str.push(*b as char);
                 } else {
                     // check if we were building a number before
-                    if num_str.len() > 0 {
-                        info_list.push(EngineInfo::PartNumber(
-                            num_str.parse().expect("num to string conversion"),
-                            num_coor, num_str.len()
-                        ));
-                        num_str.clear();
-                    }
+                    self.end_number(&mut num_str, num_coor, &mut info_list, &mut cells);
                     if *b != b'.' && *b != b'\n'{
-                        let sym = if *b == b'*' {
-                            EngineInfo::Gear(Coordinate::new(col, row), 0)
+                        let sym = if self.rule.is_gear(*b) {
+                            EngineInfo::Gear(Coordinate::new(col, row), *b, 0)
                         } else {
-                            EngineInfo::Symbol(Coordinate::new(col, row))
+                            EngineInfo::Symbol(Coordinate::new(col, row), *b)
                         };
                         info_list.push(sym);
                     }
                 }
+                if *b != b'\n' {
+                    cells.push(None);
+                }
                 col += 1;
             }
+            // the last line might not have a newline to end its number
+            self.end_number(&mut num_str, num_coor, &mut info_list, &mut cells);
             self.grid.push(info_list);
+            self.index.push(cells);
             // println!("found value: {}\n", val);
             row += 1;
             col = 0;
@@ -137,105 +272,89 @@ impl EngineSchematic {
         }
     }
 
-    fn gather_part_numbers(&self) -> Vec<u64> {
-        // let mut part_nums = Vec::new();
-        // let mut parts = Vec::with_capacity(self.grid.len());
-        let mut parts: HashMap<Coordinate, u64> = HashMap::new();
+    // turn the digits we have collected into a part number
+    fn end_number(
+        &mut self,
+        num_str: &mut String,
+        coord: Coordinate,
+        info_list: &mut Vec<EngineInfo>,
+        cells: &mut [Option<PartId>],
+    ) {
+        if num_str.is_empty() {
+            return;
+        }
+        let part = EngineInfo::PartNumber(
+            num_str.parse().expect("num to string conversion"),
+            coord, num_str.len()
+        );
+        // point every cell the number covers back at it
+        cells[part.span()].fill(Some(self.parts.len()));
+        info_list.push(part);
+        self.parts.push(part);
+        num_str.clear();
+    }
+
+    // which part number (if any) covers this cell
+    fn part_at(&self, coord: Coordinate) -> Option<PartId> {
+        *self.index.get(coord.row())?.get(coord.col())?
+    }
+
+    // flags every part (by id) that is next to at least one symbol
+    fn touched_parts(&self) -> Vec<bool> {
+        // a part can touch many symbols, only count it once
+        let mut seen = vec![false; self.parts.len()];
+        let mut parts: Vec<PartId> = Vec::with_capacity(8);
         for row in &self.grid {
             for info in row {
                 if info.is_symbol() {
                     let coord = info.coord();
                     self.search_adjacents(coord, &mut parts);
-                    // pick parts from grid and push onto list
-                    // for part in &parts {
-                    //     part_nums.push(
-                    //         *part
-                    //     );
-                    // }
-                    // parts.clear();
+                    for id in parts.drain(..) {
+                        seen[id] = true;
+                    }
                 }
             }
         }
-        parts
+        seen
+    }
+
+    fn gather_part_numbers(&self) -> Vec<u64> {
+        self.touched_parts()
             .iter()
-            .map(|(_, part)| *part)
+            .enumerate()
+            .filter(|(_, found)| **found)
+            .map(|(id, _)| self.parts[id].value())
             .collect()
     }
 
-    fn search_adjacents(&self, coord: Coordinate, locs: &mut HashMap<Coordinate, u64>) {
-        // search around radius of the coordinate
-        const RADIUS: [(i32, i32); 8] = [ // should proably be coords
-            // (row, col)
-            // upper row: ul, up, ur
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            // left and right
-            (0, -1),
-            (0, 1),
-            // bottom row: bl, b, br
-            (1, -1),
-            (1, 0),
-            (1, 1),
-        ];
-
+    fn search_adjacents(&self, coord: Coordinate, locs: &mut Vec<PartId>) {
         // println!("searching: {:?}", coord);
-
-        for (row, col) in RADIUS {
-            let sr = if row.is_negative() {
-                coord.row().checked_sub(row.abs() as usize).unwrap()
-            } else {
-                coord.row().checked_add(row.abs() as usize).unwrap()
-            };
-            let sc = if col.is_negative() {
-                coord.col().checked_sub(col.abs() as usize).unwrap()
-            } else {
-                coord.col().checked_add(col.abs() as usize).unwrap()
-            };
-            // println!("\tchecking: {}, {}", sr, sc);
-            // check if we have a symbol here
-            let r = &self.grid[sr];
-            let adj = r
-                .iter()
-                .filter(|info| !info.is_symbol() && info.in_range(sc))
-                .map(|num| {
-                    // println!("found: {:?}", num);
-                    (num.coord(), num.value())
-                });
-            for (c, x) in adj {
-                // locs.push(x);
-                // try to add in map
-                // don't if it exist
-                locs.insert(c, x);
+        for n in coord.neighbours() {
+            // println!("\tchecking: {}, {}", n.row(), n.col());
+            // check if we have a number here
+            // don't add it if it exists (a number spans cells)
+            if let Some(id) = self.part_at(n) {
+                if !locs.contains(&id) {
+                    locs.push(id);
+                }
             }
         }
-        // self.grid[loc.row()]
-        // .iter()
-        // .filter(|info| info.coord().col() == loc.col())
-        // .map(|x| x.value())
     }
 
     fn gather_gear_ratios(&self) -> Vec<u64> {
-        let mut parts: HashMap<Coordinate, u64> = HashMap::new();
+        let mut parts: Vec<PartId> = Vec::with_capacity(8);
         let mut ratios: Vec<u64> = Vec::new();
         for row in &self.grid {
             for info in row {
                 if info.is_gear() {
                     let coord = info.coord();
                     self.search_adjacents(coord, &mut parts);
-                    if parts.len() == 2 {
-                        ratios.push(parts
+                    if self.rule.adjacency.accepts(parts.len()) {
+                        ratios.push(self.rule.combine.apply(parts
                             .iter()
-                            .map(|(_, part)| *part)
-                            .fold(1, |acc, part_num| acc * part_num)
-                        );
+                            .map(|id| self.parts[*id].value())
+                        ));
                     }
-                    // pick parts from grid and push onto list
-                    // for part in &parts {
-                    //     part_nums.push(
-                    //         *part
-                    //     );
-                    // }
                     parts.clear();
                 }
             }
@@ -243,20 +362,158 @@ impl EngineSchematic {
         ratios
     }
 
+    #[allow(dead_code)] // debugging aid
     fn print(&self) {
         for (num, row) in self.grid.iter().enumerate() {
             print!("row {}: ", num);
             for item in row {
                 print!("{:?}, ", item);
             }
-            println!("");
+            println!();
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help(true))]
+struct Args {
+    /// Run Part 1
+    #[arg(short('f'), long)]
+    run_part_1: bool,
+
+    /// Run Part 2
+    #[arg(short('s'), long)]
+    run_part_2: bool,
+
+    /// Symbols that count as gears
+    #[arg(long, default_value = "*")]
+    gear_symbols: String,
+
+    /// Parts a gear must touch: exact:N, min:N or max:N
+    #[arg(long, default_value = "exact:2")]
+    gear_parts: Adjacency,
+
+    /// How the parts around a gear are combined into its ratio
+    #[arg(long, value_enum, default_value_t = Combine::Product)]
+    gear_combine: Combine,
+
+    /// Draw the schematic with parts and gears highlighted
+    #[arg(short('r'), long)]
+    render: bool,
+
+    /// Render without colours (for piping)
+    #[arg(long)]
+    no_color: bool,
+
+    /// List the numbers that are not next to any symbol
+    #[arg(short('o'), long)]
+    orphans: bool,
+
+    /// List every symbol with the parts next to it
+    #[arg(short('a'), long)]
+    adjacency: bool,
+
+    /// Print the part/symbol adjacency graph in graphviz dot format
+    #[arg(long)]
+    dot: bool,
+
+    /// Solve the parts a few rows at a time instead of loading the whole schematic
+    #[arg(long)]
+    stream: bool,
+
+    /// List groups of numbers and symbols that touch, colours them when rendering
+    #[arg(short('c'), long)]
+    components: bool,
+}
+
+impl Args {
+    fn gear_rule(&self) -> GearRule {
+        GearRule {
+            symbols: self.gear_symbols.clone().into_bytes(),
+            adjacency: self.gear_parts,
+            combine: self.gear_combine,
         }
     }
 }
 
 fn main() {
-    let sum = part2();
-    println!("sum: {}", sum);
+    // clap args parsing
+    let args = Args::parse();
+    if args.stream {
+        let (part1, part2) = solve_stream(args.gear_rule());
+        if args.run_part_1 {
+            println!("part 1: {}", part1);
+        }
+        if args.run_part_2 {
+            println!("part 2: {}", part2);
+        }
+    } else {
+        if args.run_part_1 {
+            let ans = part1();
+            println!("part 1: {}", ans);
+        }
+        if args.run_part_2 {
+            let ans = part2(args.gear_rule());
+            println!("part 2: {}", ans);
+        }
+    }
+    if args.render || args.components {
+        let mut schem = EngineSchematic::new(INPUT);
+        schem.set_gear_rule(args.gear_rule());
+        schem.parse();
+        let comps = args.components.then(|| schem.components());
+        if args.render {
+            let mut out = std::io::stdout().lock();
+            match &comps {
+                Some(comps) => schem.render_components(comps, &mut out, !args.no_color),
+                None => schem.render(&mut out, !args.no_color),
+            }.expect("failed to render schematic");
+        }
+        if let Some(comps) = comps {
+            println!("components: {}", comps.list.len());
+            for (id, comp) in comps.list.iter().enumerate() {
+                println!(
+                    "  {}: size {}, part sum {}, gears {} (ratios {})",
+                    id, comp.size(), comp.part_sum, comp.gears.len(), comp.gear_ratios
+                );
+            }
+        }
+    }
+    if args.orphans || args.adjacency || args.dot {
+        let mut schem = EngineSchematic::new(INPUT);
+        schem.set_gear_rule(args.gear_rule());
+        schem.parse();
+        let analysis = schem.analyse();
+        if args.orphans {
+            let orphans: Vec<_> = analysis.orphans().collect();
+            println!("orphan numbers: {}", orphans.len());
+            for num in orphans {
+                println!(
+                    "  {} at row {}, col {} (len {})",
+                    num.value, num.coord.row(), num.coord.col(), num.len
+                );
+            }
+        }
+        if args.adjacency {
+            for sym in &analysis.symbols {
+                let parts: Vec<String> = sym.parts
+                    .iter()
+                    .map(|id| analysis.numbers[*id].value.to_string())
+                    .collect();
+                println!(
+                    "{} at row {}, col {}{}: [{}]",
+                    sym.symbol as char, sym.coord.row(), sym.coord.col(),
+                    if sym.gear { " (gear)" } else { "" },
+                    parts.join(", ")
+                );
+            }
+        }
+        if args.dot {
+            analysis
+                .write_dot(&mut std::io::stdout().lock())
+                .expect("failed to write dot graph");
+        }
+    }
 }
 
 const INPUT: &str = 
@@ -295,8 +552,9 @@ fn part1() -> Answer {
 // - adjacent to exactly two parts
 // - gear ratio is result of multiplying numbers
 // find the gear ratios, and add them all up
-fn part2() -> Answer {
+fn part2(rule: GearRule) -> Answer {
     let mut schem = EngineSchematic::new(INPUT);
+    schem.set_gear_rule(rule);
     // iterate over all bytes in the stream
     // save locations of numbers and symbols (sparse matrix?)
     schem.parse();
@@ -309,49 +567,291 @@ fn part2() -> Answer {
     result
 }
 
+// same answers as part 1 and 2, but reading the schematic a row at a time
+fn solve_stream(rule: GearRule) -> (Answer, Answer) {
+    let file = File::open(INPUT).unwrap();
+    StreamSolver::new(BufReader::new(file), rule)
+        .fold((0, 0), |(parts, ratios), row| (
+            parts + row.parts.iter().sum::<u64>(),
+            ratios + row.ratios.iter().sum::<u64>(),
+        ))
+}
+
 #[cfg(test)]
 mod test {
     use crate::*;
+    use crate::stream::RowResult;
 
-    #[test]
-    fn test_part1() {
-        let mut schem = EngineSchematic::new("test.txt");
-        // iterate over all bytes in the stream
-        // save locations of numbers and symbols (sparse matrix?)
+    fn parsed(input: &str) -> EngineSchematic {
+        let mut schem = EngineSchematic::new(input);
         schem.parse();
+        schem
+    }
 
-        schem.print();
+    #[test]
+    fn test_part1() {
+        let schem = parsed("test.txt");
         // check sparse map for symbols next to numbers
-        let part_nums = schem.gather_gear_ratios();
-        // convert/add up the numbers for the schematic
-        for part in part_nums.iter() {
-            print!("{} ", part);
-        }
-        print!("\n");
+        let part_nums = schem.gather_part_numbers();
+        assert_eq!(part_nums, vec![467, 35, 633, 617, 592, 755, 664, 598]);
         let result: u64 = part_nums
             .into_iter()
             .sum();
-        assert_eq!(result, 467835);
+        assert_eq!(result, 4361);
     }
 
     #[test]
     fn test_part2() {
+        let schem = parsed("test.txt");
+        let ratios = schem.gather_gear_ratios();
+        assert_eq!(ratios, vec![467 * 35, 755 * 598]);
+        let result: u64 = ratios
+            .into_iter()
+            .sum();
+        assert_eq!(result, 467835);
+    }
+
+    // (file, part 1, part 2)
+    const FIXTURES: [(&str, u64, u64); 5] = [
+        // symbols in every corner and along the edges
+        ("fixtures/edge_symbols.txt", 12 + 3 + 5 + 7, 0),
+        // numbers end the line, the last one ends the file (no newline)
+        ("fixtures/line_end.txt", 12 + 34 + 56 + 78, 0),
+        // numbers with symbols all around them only count once
+        ("fixtures/multi_symbol.txt", 5 + 123, 0),
+        // the same value in different places counts every time
+        ("fixtures/duplicates.txt", 40, 10 * 10 + 10 * 10),
+        // gears next to one, two and three parts, only two counts
+        ("fixtures/gears.txt", 11 + 22 + 77 + 33 + 88 + 9, 22 * 77),
+    ];
+
+    #[test]
+    fn test_fixtures() {
+        for (input, part1, part2) in FIXTURES {
+            let schem = parsed(input);
+            let sum: u64 = schem.gather_part_numbers().into_iter().sum();
+            assert_eq!(sum, part1, "part 1 of {}", input);
+            let sum: u64 = schem.gather_gear_ratios().into_iter().sum();
+            assert_eq!(sum, part2, "part 2 of {}", input);
+            // the other views of the schematic have to agree
+            let analysis = schem.analyse();
+            let sum: u64 = analysis.numbers
+                .iter()
+                .filter(|n| !n.is_orphan())
+                .map(|n| n.value)
+                .sum();
+            assert_eq!(sum, part1, "analysis of {}", input);
+            assert_stream_matches(input, GearRule::default());
+            let mut out = Vec::new();
+            schem.render(&mut out, false).unwrap();
+            let expected = std::fs::read_to_string(input).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap().trim_end(), expected.trim_end());
+        }
+    }
+
+    #[test]
+    fn test_line_end() {
+        let schem = parsed("fixtures/line_end.txt");
+        // 78 is the very last thing in the file
+        let last = schem.parts.last().unwrap();
+        assert_eq!((last.value(), last.coord()), (78, Coordinate::new(5, 3)));
+        assert_eq!(schem.part_at(Coordinate::new(6, 3)), Some(3));
+    }
+
+    #[test]
+    fn test_gear_neighbours() {
+        let schem = parsed("fixtures/gears.txt");
+        let analysis = schem.analyse();
+        let counts: Vec<usize> = analysis.symbols.iter().map(|s| s.parts.len()).collect();
+        assert_eq!(counts, vec![1, 2, 3]);
+        let mut schem = EngineSchematic::new("fixtures/gears.txt");
+        schem.set_gear_rule(GearRule {
+            adjacency: Adjacency::Min(1),
+            ..GearRule::default()
+        });
+        schem.parse();
+        assert_eq!(schem.gather_gear_ratios(), vec![11, 22 * 77, 33 * 9 * 88]);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let schem = parsed("fixtures/duplicates.txt");
+        let analysis = schem.analyse();
+        let coords: Vec<Coordinate> = analysis.numbers.iter().map(|n| n.coord).collect();
+        assert_eq!(coords, vec![
+            Coordinate::new(0, 0), Coordinate::new(6, 0),
+            Coordinate::new(0, 2), Coordinate::new(6, 2),
+        ]);
+        assert!(analysis.numbers.iter().all(|n| n.value == 10 && n.symbols.len() == 1));
+    }
+
+    fn gear_total(rule: GearRule) -> u64 {
         let mut schem = EngineSchematic::new("test.txt");
-        // iterate over all bytes in the stream
-        // save locations of numbers and symbols (sparse matrix?)
+        schem.set_gear_rule(rule);
         schem.parse();
+        schem.gather_gear_ratios().into_iter().sum()
+    }
 
-        schem.print();
-        // check sparse map for symbols next to numbers
-        let part_nums = schem.gather_part_numbers();
-        // convert/add up the numbers for the schematic
-        for part in part_nums.iter() {
-            print!("{} ", part);
-        }
-        print!("\n");
-        let result: u64 = part_nums
-            .into_iter()
+    #[test]
+    fn test_gear_rules() {
+        assert_eq!(gear_total(GearRule::default()), 467835);
+        // every '*' touching at least one part, added up
+        let rule = GearRule {
+            adjacency: Adjacency::Min(1),
+            combine: Combine::Sum,
+            ..GearRule::default()
+        };
+        assert_eq!(gear_total(rule), 467 + 35 + 617 + 755 + 598);
+        // the biggest part of each real gear
+        let rule = GearRule {
+            combine: Combine::Max,
+            ..GearRule::default()
+        };
+        assert_eq!(gear_total(rule), 467 + 755);
+        // '#' is only next to 633
+        let rule = GearRule {
+            symbols: vec![b'#'],
+            adjacency: Adjacency::Max(1),
+            ..GearRule::default()
+        };
+        assert_eq!(gear_total(rule), 633);
+    }
+
+    #[test]
+    fn test_adjacency_from_str() {
+        assert_eq!("exact:2".parse(), Ok(Adjacency::Exact(2)));
+        assert_eq!("min:1".parse(), Ok(Adjacency::Min(1)));
+        assert_eq!("max:3".parse(), Ok(Adjacency::Max(3)));
+        assert!("2".parse::<Adjacency>().is_err());
+        assert!("most:2".parse::<Adjacency>().is_err());
+        assert!("min:x".parse::<Adjacency>().is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut schem = EngineSchematic::new("test.txt");
+        schem.parse();
+
+        // no colour gives us the input back
+        let mut out = Vec::new();
+        schem.render(&mut out, false).unwrap();
+        let expected = std::fs::read_to_string("test.txt").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let mut out = Vec::new();
+        schem.render(&mut out, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        // 467 is a part, 114 touches nothing
+        assert_eq!(lines[0], "\x1b[1;32m467\x1b[0m..\x1b[2m114\x1b[0m..");
+        // a real gear and a '*' with only one part
+        assert!(lines[1].contains("\x1b[1;33m*\x1b[0m"));
+        assert!(lines[4].contains("\x1b[1;31m*\x1b[0m"));
+        assert!(lines[3].contains("\x1b[1m#\x1b[0m"));
+    }
+
+    #[test]
+    fn test_analyse() {
+        let mut schem = EngineSchematic::new("test.txt");
+        schem.parse();
+        let analysis = schem.analyse();
+
+        let orphans: Vec<u64> = analysis.orphans().map(|n| n.value).collect();
+        assert_eq!(orphans, vec![114, 58]);
+
+        // 467 only touches the gear below it
+        let num = &analysis.numbers[0];
+        assert_eq!((num.value, num.coord, num.len), (467, Coordinate::new(0, 0), 3));
+        assert_eq!(num.symbols.len(), 1);
+        let sym = &analysis.symbols[num.symbols[0]];
+        assert_eq!((sym.symbol, sym.coord, sym.gear), (b'*', Coordinate::new(3, 1), true));
+        let parts: Vec<u64> = sym.parts.iter().map(|id| analysis.numbers[*id].value).collect();
+        assert_eq!(parts, vec![467, 35]);
+
+        // every non orphan is what part 1 adds up
+        let total: u64 = analysis.numbers
+            .iter()
+            .filter(|n| !n.is_orphan())
+            .map(|n| n.value)
             .sum();
-        assert_eq!(result, 4361);
+        assert_eq!(total, 4361);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dot() {
+        let mut schem = EngineSchematic::new("test.txt");
+        schem.parse();
+        let mut out = Vec::new();
+        schem.analyse().write_dot(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("graph schematic {\n"));
+        assert!(out.ends_with("}\n"));
+        assert!(out.contains("p0 [shape=box, label=\"467 (0,0)\"];"));
+        assert!(out.contains("p1 [shape=box, label=\"114 (0,5)\", style=dashed];"));
+        assert!(out.contains("s0 [shape=circle, label=\"* (1,3)\", style=filled, fillcolor=gold];"));
+        assert!(out.contains("s1 [shape=circle, label=\"# (3,6)\"];"));
+        assert!(out.contains("s0 -- p0;"));
+        // one edge per part next to a symbol (none touch two here)
+        assert_eq!(out.matches(" -- ").count(), 8);
+    }
+
+    // the streaming solver has to find exactly what the in memory one does
+    fn assert_stream_matches(input: &str, rule: GearRule) {
+        let mut schem = EngineSchematic::new(input);
+        schem.set_gear_rule(rule.clone());
+        schem.parse();
+
+        let file = File::open(input).unwrap();
+        let rows: Vec<_> = StreamSolver::new(BufReader::new(file), rule).collect();
+        assert_eq!(rows.len(), schem.grid.len());
+        assert!(rows.iter().enumerate().all(|(n, r)| r.row == n));
+        let parts: Vec<u64> = rows.iter().flat_map(|r| r.parts.clone()).collect();
+        let ratios: Vec<u64> = rows.iter().flat_map(|r| r.ratios.clone()).collect();
+        assert_eq!(parts, schem.gather_part_numbers());
+        assert_eq!(ratios, schem.gather_gear_ratios());
+    }
+
+    #[test]
+    fn test_stream() {
+        for input in ["test.txt", "input.txt"] {
+            assert_stream_matches(input, GearRule::default());
+            assert_stream_matches(input, GearRule {
+                symbols: b"*#$".to_vec(),
+                adjacency: Adjacency::Min(1),
+                combine: Combine::Sum,
+            });
+        }
+
+        let file = File::open("test.txt").unwrap();
+        let rows: Vec<_> = StreamSolver::new(BufReader::new(file), GearRule::default()).collect();
+        assert_eq!(rows[0], RowResult { row: 0, parts: vec![467], ratios: vec![] });
+        assert_eq!(rows[1].ratios, vec![467 * 35]);
+    }
+
+    #[test]
+    fn test_components() {
+        let mut schem = EngineSchematic::new("test.txt");
+        schem.parse();
+        let comps = schem.components();
+
+        let sizes: Vec<usize> = comps.list.iter().map(|c| c.size()).collect();
+        assert_eq!(sizes, vec![3, 1, 2, 2, 2, 1, 3, 2]);
+        let sums: Vec<u64> = comps.list.iter().map(|c| c.part_sum).collect();
+        assert_eq!(sums, vec![467 + 35, 0, 633, 617, 592, 0, 755 + 598, 664]);
+        let ratios: Vec<u64> = comps.list.iter().map(|c| c.gear_ratios).collect();
+        assert_eq!(ratios, vec![16345, 0, 0, 0, 0, 0, 451490, 0]);
+        assert_eq!(comps.list[6].gears, vec![Coordinate::new(5, 8)]);
+
+        // the '+' and 592 only touch diagonally
+        assert_eq!(comps.at(Coordinate::new(5, 5)), Some(4));
+        assert_eq!(comps.at(Coordinate::new(2, 6)), Some(4));
+        assert_eq!(comps.at(Coordinate::new(0, 1)), None);
+
+        // every cell of a number gets the same colour
+        let mut out = Vec::new();
+        schem.render_components(&comps, &mut out, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[1;31m467\x1b[0m..\x1b[1;32m114\x1b[0m..\n"));
+    }
+}
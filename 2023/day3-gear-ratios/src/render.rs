@@ -0,0 +1,72 @@
+use std::io::{self, Write};
+
+use crate::{EngineInfo, EngineSchematic, PartId};
+
+// ansi escape codes for the renderer
+const RESET: &str = "\x1b[0m";
+const PART: &str = "\x1b[1;32m"; // bold green
+const ORPHAN: &str = "\x1b[2m"; // dim
+const SYMBOL: &str = "\x1b[1m"; // bold
+const GEAR: &str = "\x1b[1;33m"; // bold yellow
+const BAD_GEAR: &str = "\x1b[1;31m"; // bold red
+
+// write text, wrapped in a colour when we have one
+fn paint(out: &mut impl Write, text: &str, style: &str, color: bool) -> io::Result<()> {
+    if color {
+        write!(out, "{}{}{}", style, text, RESET)
+    } else {
+        write!(out, "{}", text)
+    }
+}
+
+impl EngineSchematic {
+    // redraw the schematic the way it was read in
+    // - part numbers next to a symbol are highlighted
+    // - numbers no symbol touches are dimmed
+    // - gears that follow the gear rule and those that don't get their own colour
+    // without colour this gives back the original input
+    pub fn render(&self, out: &mut impl Write, color: bool) -> io::Result<()> {
+        let touched = self.touched_parts();
+        let mut parts: Vec<PartId> = Vec::with_capacity(8);
+        self.draw(out, color, |info| match info {
+            EngineInfo::PartNumber(_, coord, _) => {
+                let id = self.part_at(*coord).expect("part is indexed");
+                if touched[id] { PART } else { ORPHAN }
+            }
+            EngineInfo::Gear(coord, _, _) => {
+                self.search_adjacents(*coord, &mut parts);
+                let valid = self.rule.adjacency.accepts(parts.len());
+                parts.clear();
+                if valid { GEAR } else { BAD_GEAR }
+            }
+            EngineInfo::Symbol(_, _) => SYMBOL,
+        })
+    }
+
+    // walk the schematic and paint every item in the style we are given
+    pub fn draw<'a>(
+        &self,
+        out: &mut impl Write,
+        color: bool,
+        mut style: impl FnMut(&EngineInfo) -> &'a str,
+    ) -> io::Result<()> {
+        for (num, row) in self.grid.iter().enumerate() {
+            let mut col = 0;
+            for info in row {
+                let span = info.span();
+                // everything between items is empty space
+                write!(out, "{}", ".".repeat(span.start - col))?;
+                let text = match info {
+                    // keep any leading zeros from the input
+                    EngineInfo::PartNumber(value, _, len) => format!("{:0len$}", value, len = len),
+                    EngineInfo::Gear(_, sym, _) | EngineInfo::Symbol(_, sym) => (*sym as char).to_string(),
+                };
+                paint(out, &text, style(info), color)?;
+                col = span.end;
+            }
+            let width = self.index[num].len();
+            writeln!(out, "{}", ".".repeat(width.saturating_sub(col)))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::BufRead;
+use std::ops::Range;
+
+use crate::GearRule;
+
+// one row of the schematic, just what we need to look around it
+// both lists are in column order
+#[derive(Default)]
+struct Row {
+    // value, columns
+    parts: Vec<(u64, Range<usize>)>,
+    // col, symbol
+    symbols: Vec<(usize, u8)>,
+}
+
+impl Row {
+    fn scan(line: &[u8]) -> Self {
+        let mut row = Row::default();
+        let mut start: Option<usize> = None;
+        for (col, b) in line.iter().enumerate() {
+            if b.is_ascii_digit() {
+                start.get_or_insert(col);
+                continue;
+            }
+            // number ends when we see '.' or symbol
+            if let Some(s) = start.take() {
+                row.push_part(line, s..col);
+            }
+            if *b != b'.' && *b != b'\n' {
+                row.symbols.push((col, *b));
+            }
+        }
+        if let Some(s) = start {
+            row.push_part(line, s..line.len());
+        }
+        row
+    }
+
+    fn push_part(&mut self, line: &[u8], cols: Range<usize>) {
+        let value = std::str::from_utf8(&line[cols.clone()])
+            .expect("digits are ascii")
+            .parse()
+            .expect("num to string conversion");
+        self.parts.push((value, cols));
+    }
+
+    // is there a symbol anywhere in these columns
+    fn has_symbol(&self, lo: usize, hi: usize) -> bool {
+        let i = self.symbols.partition_point(|(c, _)| *c < lo);
+        self.symbols.get(i).is_some_and(|(c, _)| *c <= hi)
+    }
+
+    // the parts that are next to (or on) this column
+    fn parts_around(&self, col: usize) -> impl Iterator<Item = u64> + '_ {
+        let i = self.parts.partition_point(|(_, cols)| cols.end < col);
+        self.parts[i..]
+            .iter()
+            .take_while(move |(_, cols)| cols.start <= col + 1)
+            .map(|(value, _)| *value)
+    }
+}
+
+// what a row turned out to have once we could see the rows around it
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RowResult {
+    pub row: usize,
+    pub parts: Vec<u64>,
+    pub ratios: Vec<u64>,
+}
+
+// solves the schematic while only holding three rows at a time
+// - the middle row is solved once the row below it is read
+// - yields one result per row, in order
+pub struct StreamSolver<R> {
+    input: R,
+    rule: GearRule,
+    line: String,
+    row: usize,
+    prev: Row,
+    cur: Option<Row>,
+}
+
+impl<R: BufRead> StreamSolver<R> {
+    pub fn new(mut input: R, rule: GearRule) -> Self {
+        let mut line = String::new();
+        let cur = Self::read_row(&mut input, &mut line);
+        Self {
+            input,
+            rule,
+            line,
+            row: 0,
+            prev: Row::default(),
+            cur,
+        }
+    }
+
+    fn read_row(input: &mut R, line: &mut String) -> Option<Row> {
+        line.clear();
+        if input.read_line(line).unwrap() > 0 {
+            Some(Row::scan(line.as_bytes()))
+        } else {
+            None
+        }
+    }
+
+    fn solve(&self, cur: &Row, next: &Row) -> RowResult {
+        let window = [&self.prev, cur, next];
+        let parts = cur.parts
+            .iter()
+            .filter(|(_, cols)| {
+                let lo = cols.start.saturating_sub(1);
+                window.iter().any(|r| r.has_symbol(lo, cols.end))
+            })
+            .map(|(value, _)| *value)
+            .collect();
+        let mut ratios = Vec::new();
+        let mut around = Vec::with_capacity(8);
+        for (col, sym) in &cur.symbols {
+            if !self.rule.is_gear(*sym) {
+                continue;
+            }
+            around.extend(window.iter().flat_map(|r| r.parts_around(*col)));
+            if self.rule.adjacency.accepts(around.len()) {
+                ratios.push(self.rule.combine.apply(around.iter().copied()));
+            }
+            around.clear();
+        }
+        RowResult { row: self.row, parts, ratios }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamSolver<R> {
+    type Item = RowResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cur = self.cur.take()?;
+        let next = Self::read_row(&mut self.input, &mut self.line);
+        let result = self.solve(&cur, next.as_ref().unwrap_or(&Row::default()));
+        // slide the window down a row
+        self.prev = cur;
+        self.cur = next;
+        self.row += 1;
+        Some(result)
+    }
+}
@@ -1,10 +1,89 @@
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::File;
+use std::hint::black_box;
 use std::io::{BufReader, BufRead};
+use std::ops::Range;
+use std::time::Instant;
+
+use clap::{Parser, ValueEnum};
+
+mod report;
+
+use report::cascade_report;
+
+// the numbers on one side of a card
+// - numbers are < 100 in practice so they fit in a bitset,
+//   matching two sets is then just an AND and a popcount
+// - anything bigger falls back to a hash set
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum NumberSet {
+    Bits(u128),
+    Sparse(HashSet<u64>),
+}
+
+impl NumberSet {
+    fn contains(&self, n: u64) -> bool {
+        match self {
+            NumberSet::Bits(bits) => n < 128 && bits & (1 << n) != 0,
+            NumberSet::Sparse(set) => set.contains(&n),
+        }
+    }
+
+    // how many numbers both sets have
+    fn count_common(&self, other: &NumberSet) -> u64 {
+        match (self, other) {
+            (NumberSet::Bits(a), NumberSet::Bits(b)) => (a & b).count_ones() as u64,
+            (NumberSet::Sparse(set), other) | (other, NumberSet::Sparse(set)) => {
+                set.iter().filter(|n| other.contains(**n)).count() as u64
+            }
+        }
+    }
+}
+
+impl FromIterator<u64> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let nums: Vec<u64> = iter.into_iter().collect();
+        if nums.iter().all(|n| *n < 128) {
+            NumberSet::Bits(nums.iter().fold(0, |bits, n| bits | (1 << n)))
+        } else {
+            NumberSet::Sparse(nums.into_iter().collect())
+        }
+    }
+}
 
 struct Card {
     id: u32,
-    winning: Vec<u64>,
-    chosen: Vec<u64>,
+    winning: NumberSet,
+    chosen: NumberSet,
+}
+
+// split a card line into its id, winning and chosen numbers
+fn parse_lists(s: &str) -> (u32, Vec<u64>, Vec<u64>) {
+    let mut line = s.split(':');
+    let card: u32 = line
+        .next()
+        .expect("no card")
+        .split(' ')
+        .next_back()
+        .expect("no last")
+        .parse()
+        .unwrap();
+    line = line.next().unwrap().split('|');
+    let winning: Vec<u64> = parse_numbers(line.next().unwrap());
+    let chosen: Vec<u64> = parse_numbers(line.next().unwrap());
+    (card, winning, chosen)
+}
+
+fn parse_numbers(list: &str) -> Vec<u64> {
+    list
+        .trim()
+        .split(' ')
+        .filter(|s| !s.is_empty())
+        .map(|n| {
+            // println!("found: {}", n);
+            n.parse().unwrap()})
+        .collect()
 }
 
 use std::str::FromStr;
@@ -12,84 +91,321 @@ impl FromStr for Card {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut line = s.split(":");
-        let card: u32 = line
-            .next()
-            .expect("no card")
-            .split(" ")
-            .last()
-            .expect("no last")
-            .parse()
-            .unwrap();
-        line = line.next().unwrap().split("|");
-        let winning: Vec<u64> = line
-            .next()
-            .unwrap()
-            .trim()
-            .split(" ")
-            .filter(|s| !s.is_empty())
-            .map(|n| n.parse().unwrap())
-            .collect();
-        let chosen: Vec<u64> = line
-            .next()
-            .unwrap()
-            .trim()
-            .split(" ")
-            .filter(|s| !s.is_empty())
-            .map(|n| {
-                // println!("found: {}", n);
-                n.parse().unwrap()})
-            .collect();
-        Ok(Card { id: card, winning, chosen })
+        let (card, winning, chosen) = parse_lists(s);
+        Ok(Card {
+            id: card,
+            winning: winning.into_iter().collect(),
+            chosen: chosen.into_iter().collect(),
+        })
     }
 }
 
+// the way we used to match: check every chosen number against every winning one
+fn count_matches_naive(winning: &[u64], chosen: &[u64]) -> u64 {
+    chosen
+        .iter()
+        .filter(|c| winning.contains(c))
+        .count() as u64
+}
+
 impl Card {
     fn num_winning(&self) -> u64 {
-        self.chosen
-            .iter()
-            .map(|c| self.winning
-                .iter()
-                .any(|w| *w == *c)
-            )
-            .filter(|n| *n == true)
-            .count() as u64
+        self.winning.count_common(&self.chosen)
+    }
+
+    fn points(&self, rule: &ScoringRule) -> u64 {
+        rule.points(self.num_winning())
+    }
+}
+
+// how many points a card is worth for its matches
+// - no matches is always worth nothing
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ScoringRule {
+    // 1, 2, 4, 8, ...
+    Doubling,
+    // 1, 2, 3, 4, ...
+    Linear,
+    // 1, 1, 2, 3, 5, ...
+    Fibonacci,
+    // points for 1, 2, 3, ... matches, the last entry covers any more
+    Table(Vec<u64>),
+}
+
+impl ScoringRule {
+    fn points(&self, matches: u64) -> u64 {
+        if matches == 0 {
+            return 0;
+        }
+        match self {
+            // big enough to overflow, stay at the max
+            ScoringRule::Doubling => u32::try_from(matches - 1)
+                .ok()
+                .and_then(|n| 1u64.checked_shl(n))
+                .unwrap_or(u64::MAX),
+            ScoringRule::Linear => matches,
+            ScoringRule::Fibonacci => {
+                let (mut a, mut b) = (0u64, 1u64);
+                for _ in 1..matches {
+                    (a, b) = (b, a.saturating_add(b));
+                }
+                b
+            }
+            ScoringRule::Table(table) => {
+                let i = usize::try_from(matches - 1).unwrap_or(usize::MAX);
+                table.get(i).or(table.last()).copied().unwrap_or(0)
+            }
+        }
+    }
+}
+
+impl FromStr for ScoringRule {
+    type Err = String;
+
+    // doubling, linear, fibonacci or table:N,N,...
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "doubling" => Ok(ScoringRule::Doubling),
+            "linear" => Ok(ScoringRule::Linear),
+            "fibonacci" => Ok(ScoringRule::Fibonacci),
+            rule => {
+                let table = rule
+                    .strip_prefix("table:")
+                    .ok_or(format!("unknown scoring rule '{}'", rule))?;
+                table
+                    .split(',')
+                    .map(|n| n.trim().parse().map_err(|e| format!("bad points '{}': {}", n, e)))
+                    .collect::<Result<Vec<u64>, _>>()
+                    .map(ScoringRule::Table)
+            }
+        }
+    }
+}
+
+// what to do when a card wins copies of cards past the end of the pile
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Overflow {
+    // copies past the last card are lost
+    Clamp,
+    // the input is wrong, stop
+    Error,
+    // copies carry on from the first card, those cards have already
+    // been scratched so the extra copies don't win anything more
+    Wrap,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct OverflowError {
+    card: u32,
+    wins: u64,
+    // cards left after this one
+    remaining: usize,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "card {} wins copies of {} cards but only {} come after it",
+            self.card, self.wins, self.remaining
+        )
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+// count how many of each card we end up with, original plus copies
+// - every copy of a card wins the same as the original
+// - so card i hands history[i] copies to each card it wins
+fn cascade(cards: &[Card], overflow: Overflow) -> Result<Vec<u64>, OverflowError> {
+    cascade_with(cards, overflow, |_, _, _| {})
+}
+
+// the cascade, telling `grant` every time a card (by position)
+// hands copies to another: (from, to, copies)
+fn cascade_with(
+    cards: &[Card],
+    overflow: Overflow,
+    mut grant: impl FnMut(usize, usize, u64),
+) -> Result<Vec<u64>, OverflowError> {
+    // we start with one of every card
+    let mut history: Vec<u64> = vec![1; cards.len()];
+    for pos in 0..cards.len() {
+        let num_cards = history[pos];
+        let wins = wins_for(cards, pos, overflow)?;
+        // then we increment n cards ahead ...
+        for x in 1..=wins {
+            let to = (pos + x) % cards.len();
+            history[to] += num_cards;
+            grant(pos, to, num_cards);
+        }
+    }
+    Ok(history)
+}
+
+// how many cards after this one it hands copies to
+fn wins_for(cards: &[Card], pos: usize, overflow: Overflow) -> Result<usize, OverflowError> {
+    let card = &cards[pos];
+    let num_winning = card.num_winning();
+    let remaining = cards.len() - pos - 1;
+    match usize::try_from(num_winning) {
+        Ok(wins) if wins <= remaining => Ok(wins),
+        _ => match overflow {
+            Overflow::Clamp => Ok(remaining),
+            Overflow::Error => Err(OverflowError {
+                card: card.id,
+                wins: num_winning,
+                remaining,
+            }),
+            // a card can't win copies of itself, so at most once round
+            Overflow::Wrap => Ok(num_winning.min(cards.len() as u64 - 1) as usize),
+        },
     }
+}
+
+// the same count worked from the last card back to the first
+// - total[i] is every card that card i ends up giving us, itself included
+// - the cards it wins all come after it, so their totals are already known
+// - with a running sum of the totals behind us each card is O(1)
+// the totals add up to the same answer as the forward cascade
+// (wrapped copies land on cards we have already done, so that needs the forward one)
+fn cascade_backward(cards: &[Card], overflow: Overflow) -> Result<Vec<u64>, OverflowError> {
+    assert_ne!(overflow, Overflow::Wrap, "wrapping copies needs the forward cascade");
+    let mut total: Vec<u64> = vec![0; cards.len()];
+    // suffix[i] = total[i] + total[i+1] + ...
+    let mut suffix: Vec<u64> = vec![0; cards.len() + 1];
+    // report the first card that runs off the end, like the forward cascade
+    let mut error = None;
+    for pos in (0..cards.len()).rev() {
+        let wins = match wins_for(cards, pos, overflow) {
+            Ok(wins) => wins,
+            Err(e) => {
+                error = Some(e);
+                0
+            }
+        };
+        total[pos] = 1 + suffix[pos + 1] - suffix[pos + 1 + wins];
+        suffix[pos] = suffix[pos + 1] + total[pos];
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(total),
+    }
+}
+
+// which way to count the copies in part 2
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Strategy {
+    // hand copies forward card by card
+    Forward,
+    // total up from the last card back
+    Backward,
+    // run and time both, they have to agree
+    Compare,
+}
+
+// a big pile of cards to try the strategies on
+// - cards come in blocks of 10 and never win past the end of their block,
+//   copies still cascade but the counts stay small enough for u64
+// - matches are picked by a xorshift seeded with `seed`
+fn synthetic_cards(n: usize, seed: u64) -> Vec<Card> {
+    const BLOCK: usize = 10;
+    // winning numbers are 1..=10, chosen ones over 50 never match
+    let winning: NumberSet = (1..=BLOCK as u64).collect();
+    let mut state = seed.max(1);
+    (0..n)
+        .map(|pos| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let left = (BLOCK - 1 - pos % BLOCK) as u64;
+            let matches = state % (left + 1);
+            Card {
+                id: pos as u32 + 1,
+                winning: winning.clone(),
+                chosen: (1..=matches).chain(51..51 + BLOCK as u64 - matches).collect(),
+            }
+        })
+        .collect()
+}
 
-    fn points(&self) -> u64 {
-        Self::calculate_points(self.num_winning() - 1)
+// what we found putting the cards in id order
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CardCheck {
+    // the cards didn't come in id order
+    reordered: bool,
+    // ids with no card
+    gaps: Vec<Range<u32>>,
+    // ids we saw more than once, only the first card is kept
+    duplicates: Vec<u32>,
+}
+
+impl CardCheck {
+    fn is_clean(&self) -> bool {
+        !self.reordered && self.gaps.is_empty() && self.duplicates.is_empty()
     }
+}
 
-    fn calculate_points(winning: u64) -> u64 {
-        1 << (winning - 1)
+// sort the cards by id and drop repeated ids
+// - wins then go to the next n cards by id, whatever order they came in
+fn sort_cards(cards: &mut Vec<Card>) -> CardCheck {
+    let mut check = CardCheck {
+        reordered: !cards.windows(2).all(|w| w[0].id <= w[1].id),
+        ..CardCheck::default()
+    };
+    // stable, so the first card read with an id is the one we keep
+    cards.sort_by_key(|c| c.id);
+    cards.dedup_by(|later, kept| {
+        let dup = later.id == kept.id;
+        if dup {
+            check.duplicates.push(later.id);
+        }
+        dup
+    });
+    // cards are numbered from 1
+    let mut expected = 1;
+    for card in cards.iter() {
+        if card.id > expected {
+            check.gaps.push(expected..card.id);
+        }
+        expected = card.id + 1;
     }
+    check
 }
 
 type PuzzleRepr = Vec<Card>;
 
 struct Puzzle {
-    input: BufReader<File>,
     repr: PuzzleRepr,
+    check: CardCheck,
 }
 
 impl Puzzle {
     fn new(input: &str) -> Self {
         let file = File::open(input).unwrap();
         let reader = BufReader::new(file);
-        Self {
-            input: reader,
-            repr: Vec::new(),
-        }
+        Self::parse(reader)
     }
 
-    fn parse(&mut self) {
+    // read every card once, both parts work off the same cards
+    fn parse(mut input: impl BufRead) -> Self {
+        let mut cards = Vec::new();
         let mut string = String::new();
-        while self.input.read_line(&mut string).unwrap() > 0 {
+        while input.read_line(&mut string).unwrap() > 0 {
             // print!("{}", string);
             let card = Card::from_str(&string).unwrap();
-            self.repr.push(card);
+            cards.push(card);
             string.clear();
         }
+        Self::from_cards(cards)
+    }
+
+    fn from_cards(mut cards: Vec<Card>) -> Self {
+        let check = sort_cards(&mut cards);
+        Self {
+            repr: cards,
+            check,
+        }
     }
 
     // now the gondola moves!
@@ -99,20 +415,14 @@ impl Puzzle {
     // - winning numbers
     // - chosen numbers
     // points calculated by doubling for each matching
-    fn part1_solution(&mut self) -> Vec<u64> {
+    // (or however else the scoring rule says)
+    fn part1_solution(&self, rule: &ScoringRule) -> Vec<u64> {
         // println!("part 1 .... ");
-        let mut string = String::new();
-        let mut card_points = Vec::new();
-        while self.input.read_line(&mut string).unwrap() > 0 {
-            // print!("{}", string);
-            let card = Card::from_str(&string).unwrap();
-            let num_winning: u64 = card.num_winning();
-            if num_winning > 0 {
-                card_points.push(Card::calculate_points(num_winning));
-            }
-            string.clear();
-        }
-        card_points
+        self.repr
+            .iter()
+            .map(|card| card.points(rule))
+            .filter(|points| *points > 0)
+            .collect()
     }
 
     // wait, so the rules were on the back the whole time??
@@ -121,48 +431,13 @@ impl Puzzle {
     // - you win n copies of cards below you ..
     // - scratch cards copies are scored like before ... 
     // - do this for every origin and copy card!
-    fn part2_solution(&mut self) -> Vec<u64> {
-        self.parse();
-        // keep a history of which cards I have seen
-        let mut history: Vec<u64> = Vec::with_capacity(self.repr.len());
-        unsafe {
-            history.set_len(self.repr.len())
-        };
-        for card in &self.repr {
-            let num_winning = card.num_winning();
-            // we must increment the number of cards ahead that we have seen
-            // println!("checking: {}", card.id);
-            let id = (card.id - 1) as usize;
-            // we increment ourselves first so we know how many cards we have
-            let num_cards = history[id as usize] + 1;
-            history[id as usize] = num_cards;
-            if num_winning > 0 {
-                // then we increment n cards ahead ...
-                // this is okay to do since cards that do
-                // not win do not contribute to the score
-                // println!("\twinning!: {}, cards: {}", num_winning, num_cards);
-                for x in id+1..id+1 + num_winning as usize {
-                    history[x as usize] += 1 * num_cards;
-                }
-            }
-        }
-        // println!("counting cards ...");
-        // for (i, x) in history.iter().enumerate() {
-        //     println!("card {} count: {}", i+1, *x);
-        // }
+    fn part2_solution(&self, overflow: Overflow) -> Result<Vec<u64>, OverflowError> {
         // then for each card calculate the score, and sum them up
         // scratch that, we only want to know the number of cards ...
-        history.iter()
-            .enumerate()
-            // .filter(|(id, _)| self.repr[*id].num_winning() > 0)
-            .map(|(id, count)| {
-                // println!("{} of card {}, points: {}", count, id+1, self.repr[id].points());
-                // self.repr[id].points() * count
-                *count
-            })
-            .collect()
+        cascade(&self.repr, overflow)
     }
 
+    #[allow(dead_code)] // debugging aid
     fn print(&self) {
         for x in self.repr.iter() {
             println!("{}", x.id);
@@ -170,19 +445,112 @@ impl Puzzle {
     }
 }
 
+#[derive(Parser, Debug)]
+#[command(arg_required_else_help(true))]
+struct Args {
+    /// Run Part 1
+    #[arg(short('f'), long)]
+    run_part_1: bool,
+
+    /// Run Part 2
+    #[arg(short('s'), long)]
+    run_part_2: bool,
+
+    /// How cards score in part 1: doubling, linear, fibonacci or table:N,N,...
+    #[arg(long, default_value = "doubling")]
+    scoring: ScoringRule,
+
+    /// What to do with copies won past the last card
+    #[arg(long, value_enum, default_value_t = Overflow::Clamp)]
+    overflow: Overflow,
+
+    /// How part 2 counts the copies
+    #[arg(long, value_enum, default_value_t = Strategy::Forward)]
+    strategy: Strategy,
+
+    /// Use N generated cards instead of the input
+    #[arg(long, value_name = "N")]
+    synthetic: Option<usize>,
+
+    /// List every card with its matches, copies and where they came from
+    #[arg(short('r'), long)]
+    report: bool,
+
+    /// Report cards that are out of order, missing or repeated
+    #[arg(short('c'), long)]
+    check: bool,
+
+    /// Print the copy cascade as a graphviz dot graph
+    #[arg(long)]
+    dot: bool,
+
+    /// Time bitset matching against the pairwise compare, over N rounds
+    #[arg(long, value_name = "ROUNDS")]
+    bench: Option<usize>,
+}
+
 fn main() {
-    let sum = part2();
-    println!("sum: {}", sum);
+    // clap args parsing
+    let args = Args::parse();
+    let puzzle = match args.synthetic {
+        Some(n) => Puzzle::from_cards(synthetic_cards(n, 0x5eed)),
+        None => Puzzle::new(INPUT),
+    };
+    if args.run_part_1 {
+        let ans = part1(&puzzle, &args.scoring);
+        println!("part 1: {}", ans);
+    }
+    if args.run_part_2 {
+        if args.overflow == Overflow::Wrap && args.strategy != Strategy::Forward {
+            eprintln!("part 2: wrapping copies only works with the forward strategy");
+        } else {
+            match part2(&puzzle, args.overflow, args.strategy) {
+                Ok(ans) => println!("part 2: {}", ans),
+                Err(e) => eprintln!("part 2: {}", e),
+            }
+        }
+    }
+    if args.check {
+        let check = &puzzle.check;
+        println!("cards: {}", puzzle.repr.len());
+        if check.is_clean() {
+            println!("all cards in order");
+        }
+        if check.reordered {
+            println!("cards are out of order");
+        }
+        for gap in &check.gaps {
+            println!("missing: {}..{}", gap.start, gap.end);
+        }
+        for id in &check.duplicates {
+            println!("duplicate: {}", id);
+        }
+    }
+    if args.report || args.dot {
+        match cascade_report(&puzzle.repr, args.overflow) {
+            Ok(report) => {
+                let mut out = std::io::stdout().lock();
+                if args.report {
+                    report::write_table(&report, &mut out).expect("failed to write report");
+                }
+                if args.dot {
+                    report::write_dot(&report, &mut out).expect("failed to write dot graph");
+                }
+            }
+            Err(e) => eprintln!("report: {}", e),
+        }
+    }
+    if let Some(rounds) = args.bench {
+        bench(rounds);
+    }
 }
 
 type Answer = u64;
 
 const INPUT: &str = "input.txt";
 
-fn part1() -> Answer {
-    let mut puzzle = Puzzle::new(INPUT);
-    // puzzle.parse();
-    let items = puzzle.part1_solution();
+fn part1(puzzle: &Puzzle, rule: &ScoringRule) -> Answer {
+    let items = puzzle.part1_solution(rule);
     // for x in items.iter() {
     //     print!("{} ", x);
     // }
@@ -195,10 +563,12 @@ fn part1() -> Answer {
     result
 }
 
-fn part2() -> Answer {
-    let mut puzzle = Puzzle::new(INPUT);
-    // puzzle.parse();
-    let items = puzzle.part2_solution();
+fn part2(puzzle: &Puzzle, overflow: Overflow, strategy: Strategy) -> Result<Answer, OverflowError> {
+    let items = match strategy {
+        Strategy::Forward => puzzle.part2_solution(overflow)?,
+        Strategy::Backward => cascade_backward(&puzzle.repr, overflow)?,
+        Strategy::Compare => return compare_strategies(&puzzle.repr, overflow),
+    };
     // for x in items.iter() {
     //     print!("{} ", x);
     // }
@@ -206,7 +576,63 @@ fn part2() -> Answer {
     let result: u64 = items
         .into_iter()
         .sum();
-    result
+    Ok(result)
+}
+
+// run both strategies, print how long each took and check they agree
+fn compare_strategies(cards: &[Card], overflow: Overflow) -> Result<Answer, OverflowError> {
+    let start = Instant::now();
+    let forward: u64 = cascade(cards, overflow)?.into_iter().sum();
+    let forward_time = start.elapsed();
+
+    let start = Instant::now();
+    let backward: u64 = cascade_backward(cards, overflow)?.into_iter().sum();
+    let backward_time = start.elapsed();
+
+    println!("{} cards", cards.len());
+    println!("forward:  {} in {:?}", forward, forward_time);
+    println!("backward: {} in {:?}", backward, backward_time);
+    assert_eq!(forward, backward, "both strategies should count the same cards");
+    Ok(forward)
+}
+
+// match every card in the input `rounds` times with both ways of counting
+fn bench(rounds: usize) {
+    let lines: Vec<String> = BufReader::new(File::open(INPUT).unwrap())
+        .lines()
+        .map(|l| l.unwrap())
+        .collect();
+    let cards: Vec<Card> = lines.iter().map(|l| Card::from_str(l).unwrap()).collect();
+    let lists: Vec<(Vec<u64>, Vec<u64>)> = lines
+        .iter()
+        .map(|l| {
+            let (_, winning, chosen) = parse_lists(l);
+            (winning, chosen)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut bitset = 0;
+    for _ in 0..rounds {
+        for card in &cards {
+            bitset += black_box(card).num_winning();
+        }
+    }
+    let bitset_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut naive = 0;
+    for _ in 0..rounds {
+        for (winning, chosen) in &lists {
+            naive += count_matches_naive(black_box(winning), black_box(chosen));
+        }
+    }
+    let naive_time = start.elapsed();
+
+    assert_eq!(bitset, naive, "both ways should find the same matches");
+    println!("{} cards, {} rounds", cards.len(), rounds);
+    println!("bitset:   {:?} ({:?} per round)", bitset_time, bitset_time / rounds.max(1) as u32);
+    println!("pairwise: {:?} ({:?} per round)", naive_time, naive_time / rounds.max(1) as u32);
 }
 
 #[cfg(test)]
@@ -216,16 +642,16 @@ mod test {
     const TEST: &str = "test.txt";
 
     #[test]
+    #[cfg_attr(miri, ignore)] // reads files
     fn test_part1() {
         const RESULT: u64 = 13;
-        let mut puzzle = Puzzle::new(TEST);
-        // puzzle.parse();
+        let puzzle = Puzzle::new(TEST);
         // puzzle.print();
-        let items = puzzle.part1_solution();
+        let items = puzzle.part1_solution(&ScoringRule::Doubling);
         for x in items.iter() {
             print!("{} ", x);
         }
-        print!("\n");
+        println!();
         let result: u64 = items
             .into_iter()
             .sum();
@@ -233,19 +659,235 @@ mod test {
     }
 
     #[test]
+    #[cfg_attr(miri, ignore)] // reads files
     fn test_part2() {
         const RESULT: u64 = 30;
-        let mut puzzle = Puzzle::new(TEST);
-        // puzzle.parse();
+        let puzzle = Puzzle::new(TEST);
         // puzzle.print();
-        let items = puzzle.part2_solution();
+        let items = puzzle.part2_solution(Overflow::Error).unwrap();
         for x in items.iter() {
             print!("{} ", x);
         }
-        print!("\n");
+        println!();
         let result: u64 = items
             .into_iter()
             .sum();
         assert_eq!(result, RESULT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_number_set() {
+        let small: NumberSet = [1, 21, 53, 99].into_iter().collect();
+        assert!(matches!(small, NumberSet::Bits(_)));
+        assert!(small.contains(53) && !small.contains(54) && !small.contains(1000));
+
+        // too big for the bitset
+        let big: NumberSet = [1, 21, 500].into_iter().collect();
+        assert!(matches!(big, NumberSet::Sparse(_)));
+        assert_eq!(big.count_common(&small), 2);
+        assert_eq!(small.count_common(&big), 2);
+
+        let other: NumberSet = [21, 99, 127].into_iter().collect();
+        assert_eq!(small.count_common(&other), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // reads files
+    fn test_matches_naive() {
+        let lines = std::fs::read_to_string(TEST).unwrap();
+        for line in lines.lines() {
+            let card = Card::from_str(line).unwrap();
+            let (_, winning, chosen) = parse_lists(line);
+            assert_eq!(card.num_winning(), count_matches_naive(&winning, &chosen));
+        }
+    }
+
+    // the cascade tests only use cards built from strings
+    // so they can run under miri (`cargo +nightly miri test`)
+    const EXAMPLE: [&str; 6] = [
+        "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+        "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+        "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+        "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+        "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+        "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+    ];
+
+    // the last card wins 2, the one before it 3
+    const OVERFLOWING: [&str; 4] = [
+        "Card 1: 1 2 3 | 7 8 9",
+        "Card 2: 1 2 3 | 1 8 9",
+        "Card 3: 1 2 3 | 1 2 3",
+        "Card 4: 1 2 3 | 1 2 9",
+    ];
+
+    fn cards(lines: &[&str]) -> Vec<Card> {
+        lines.iter().map(|l| Card::from_str(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_cascade() {
+        let cards = cards(&EXAMPLE);
+        for overflow in [Overflow::Clamp, Overflow::Error, Overflow::Wrap] {
+            let counts = cascade(&cards, overflow).unwrap();
+            assert_eq!(counts, vec![1, 2, 4, 8, 14, 1]);
+        }
+        assert_eq!(cascade(&[], Overflow::Error), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_cascade_overflow() {
+        let cards = cards(&OVERFLOWING);
+        // card 3 has 2 copies and wins cards 4 (and off the end twice)
+        assert_eq!(cascade(&cards, Overflow::Clamp), Ok(vec![1, 1, 2, 3]));
+        assert_eq!(
+            cascade(&cards, Overflow::Error),
+            Err(OverflowError { card: 3, wins: 3, remaining: 1 })
+        );
+        // card 3 wraps onto 1 and 2, card 4 (3 copies) onto 1 and 2 again
+        assert_eq!(cascade(&cards, Overflow::Wrap), Ok(vec![1 + 2 + 3, 1 + 2 + 3, 2, 3]));
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        let rules = [
+            (ScoringRule::Doubling, [0, 1, 2, 4, 8, 16]),
+            (ScoringRule::Linear, [0, 1, 2, 3, 4, 5]),
+            (ScoringRule::Fibonacci, [0, 1, 1, 2, 3, 5]),
+            (ScoringRule::Table(vec![1, 10, 100]), [0, 1, 10, 100, 100, 100]),
+            (ScoringRule::Table(vec![]), [0; 6]),
+        ];
+        for (rule, points) in rules {
+            for (matches, expected) in points.iter().enumerate() {
+                assert_eq!(rule.points(matches as u64), *expected, "{:?} {}", rule, matches);
+            }
+        }
+        // too many matches to fit stays at the top
+        assert_eq!(ScoringRule::Doubling.points(100), u64::MAX);
+        assert_eq!(ScoringRule::Fibonacci.points(1000), u64::MAX);
+    }
+
+    #[test]
+    fn test_card_points() {
+        // matches are 4, 2, 2, 1, 0, 0
+        let cards = cards(&EXAMPLE);
+        let total = |rule: ScoringRule| -> u64 { cards.iter().map(|c| c.points(&rule)).sum() };
+        assert_eq!(total(ScoringRule::Doubling), 13);
+        assert_eq!(total(ScoringRule::Linear), 4 + 2 + 2 + 1);
+        assert_eq!(total(ScoringRule::Fibonacci), 3 + 1 + 1 + 1);
+        assert_eq!(total("table:1,10,100".parse().unwrap()), 100 + 10 + 10 + 1);
+    }
+
+    #[test]
+    fn test_scoring_from_str() {
+        assert_eq!("doubling".parse(), Ok(ScoringRule::Doubling));
+        assert_eq!("linear".parse(), Ok(ScoringRule::Linear));
+        assert_eq!("fibonacci".parse(), Ok(ScoringRule::Fibonacci));
+        assert_eq!("table:1, 3,5".parse(), Ok(ScoringRule::Table(vec![1, 3, 5])));
+        assert!("table:1,x".parse::<ScoringRule>().is_err());
+        assert!("squares".parse::<ScoringRule>().is_err());
+    }
+
+    #[test]
+    fn test_cascade_report() {
+        let cards = cards(&EXAMPLE);
+        let report = cascade_report(&cards, Overflow::Error).unwrap();
+        let copies: Vec<u64> = report.iter().map(|r| r.copies).collect();
+        assert_eq!(copies, cascade(&cards, Overflow::Error).unwrap());
+        let matches: Vec<u64> = report.iter().map(|r| r.matches).collect();
+        assert_eq!(matches, vec![4, 2, 2, 1, 0, 0]);
+        assert_eq!(report[0].granted_by, vec![]);
+        assert_eq!(report[3].granted_by, vec![(1, 1), (2, 2), (3, 4)]);
+        // every copy came from somewhere
+        assert!(report.iter().all(|r| r.granted_by.iter().map(|(_, n)| n).sum::<u64>() == r.copies - 1));
+
+        let mut out = Vec::new();
+        report::write_table(&report, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().nth(4).unwrap(), "   4        1       8  1 (x1), 2 (x2), 3 (x4)");
+
+        let mut out = Vec::new();
+        report::write_dot(&report, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("digraph cascade {\n"));
+        assert!(out.contains("    c4 [label=\"card 4\\n1 matches, 8 copies\"];"));
+        assert!(out.contains("    c3 -> c4 [label=\"x4\"];"));
+        assert_eq!(out.matches(" -> ").count(), 4 + 2 + 2 + 1);
+    }
+
+    #[test]
+    fn test_sort_cards() {
+        let mut sorted = cards(&EXAMPLE);
+        assert!(sort_cards(&mut sorted).is_clean());
+
+        // shuffled, card 3 is missing and card 5 is in twice
+        let mut shuffled = cards(&[
+            EXAMPLE[5], EXAMPLE[1], EXAMPLE[4], EXAMPLE[0],
+            "Card 5: 1 2 3 | 1 2 3", EXAMPLE[3],
+        ]);
+        let check = sort_cards(&mut shuffled);
+        let missing = 3..4;
+        assert_eq!(check, CardCheck { reordered: true, gaps: vec![missing], duplicates: vec![5] });
+        let ids: Vec<u32> = shuffled.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![1, 2, 4, 5, 6]);
+        // the first card 5 we read is the one kept
+        assert_eq!(shuffled[3].num_winning(), 0);
+
+        // wins skip the missing card: card 1 (4 matches) gets 2, 4, 5 and 6,
+        // card 2 (2 copies, 2 matches) gets 4 and 5, card 4 (4 copies, 1 match) gets 5
+        assert_eq!(cascade(&shuffled, Overflow::Error), Ok(vec![1, 2, 4, 8, 2]));
+
+        let mut sparse = cards(&["Card 3: 1 | 1", "Card 7: 1 | 2"]);
+        assert_eq!(sort_cards(&mut sparse).gaps, vec![1..3, 4..7]);
+    }
+
+    #[test]
+    fn test_one_parse() {
+        // both parts off the same cards, read from a string
+        let input = EXAMPLE.join("\n");
+        let puzzle = Puzzle::parse(input.as_bytes());
+        assert_eq!(puzzle.repr.len(), 6);
+        assert_eq!(part1(&puzzle, &ScoringRule::Doubling), 13);
+        assert_eq!(part2(&puzzle, Overflow::Error, Strategy::Forward), Ok(30));
+        // and again, nothing is used up
+        assert_eq!(part1(&puzzle, &ScoringRule::Doubling), 13);
+
+        let puzzle = Puzzle::from_cards(cards(&[EXAMPLE[1], EXAMPLE[0]]));
+        assert!(puzzle.check.reordered);
+        assert_eq!(part2(&puzzle, Overflow::Clamp, Strategy::Backward), Ok(1 + 2));
+    }
+
+    #[test]
+    fn test_cascade_backward() {
+        let example = cards(&EXAMPLE);
+        // cards 1..6 end up giving 15, 7, 4, 2, 1 and 1 cards
+        assert_eq!(cascade_backward(&example, Overflow::Error), Ok(vec![15, 7, 4, 2, 1, 1]));
+
+        let overflowing = cards(&OVERFLOWING);
+        for overflow in [Overflow::Clamp, Overflow::Error] {
+            let forward = cascade(&overflowing, overflow).map(|c| c.iter().sum::<u64>());
+            let backward = cascade_backward(&overflowing, overflow).map(|c| c.iter().sum::<u64>());
+            assert_eq!(forward, backward);
+        }
+        assert_eq!(cascade_backward(&[], Overflow::Error), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_synthetic() {
+        let cards = synthetic_cards(1000, 42);
+        assert_eq!(cards.len(), 1000);
+        // no card wins past the end of its block
+        assert!(cards.iter().enumerate().all(|(pos, c)| c.num_winning() as usize <= 9 - pos % 10));
+        assert!(cards.iter().any(|c| c.num_winning() > 0));
+        let puzzle = Puzzle::from_cards(cards);
+        assert!(puzzle.check.is_clean());
+        assert_eq!(
+            part2(&puzzle, Overflow::Error, Strategy::Forward),
+            part2(&puzzle, Overflow::Error, Strategy::Backward)
+        );
+        assert_eq!(
+            part2(&puzzle, Overflow::Error, Strategy::Compare),
+            part2(&puzzle, Overflow::Error, Strategy::Forward)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::{self, Write};
+
+use crate::{cascade_with, Card, Overflow, OverflowError};
+
+// where a card's copies came from
+#[derive(Debug, PartialEq, Eq)]
+pub struct CardReport {
+    pub id: u32,
+    pub matches: u64,
+    // the original plus every copy won
+    pub copies: u64,
+    // (card id, copies handed over) in the order they were won
+    pub granted_by: Vec<(u32, u64)>,
+}
+
+// run the cascade and keep track of who gave what to whom
+pub fn cascade_report(cards: &[Card], overflow: Overflow) -> Result<Vec<CardReport>, OverflowError> {
+    let mut granted_by: Vec<Vec<(u32, u64)>> = vec![Vec::new(); cards.len()];
+    let copies = cascade_with(cards, overflow, |from, to, n| {
+        granted_by[to].push((cards[from].id, n));
+    })?;
+    Ok(cards
+        .iter()
+        .zip(copies)
+        .zip(granted_by)
+        .map(|((card, copies), granted_by)| CardReport {
+            id: card.id,
+            matches: card.num_winning(),
+            copies,
+            granted_by,
+        })
+        .collect())
+}
+
+pub fn write_table(report: &[CardReport], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "card  matches  copies  granted by")?;
+    for card in report {
+        let granted: Vec<String> = card.granted_by
+            .iter()
+            .map(|(id, n)| format!("{} (x{})", id, n))
+            .collect();
+        writeln!(
+            out,
+            "{:>4} {:>8} {:>7}  {}",
+            card.id, card.matches, card.copies, granted.join(", ")
+        )?;
+    }
+    let total: u64 = report.iter().map(|c| c.copies).sum();
+    writeln!(out, "total: {}", total)
+}
+
+// cards are nodes, an edge is a card handing out copies to a later one
+pub fn write_dot(report: &[CardReport], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "digraph cascade {{")?;
+    for card in report {
+        writeln!(
+            out,
+            "    c{} [label=\"card {}\\n{} matches, {} copies\"];",
+            card.id, card.id, card.matches, card.copies
+        )?;
+    }
+    for card in report {
+        for (from, n) in &card.granted_by {
+            writeln!(out, "    c{} -> c{} [label=\"x{}\"];", from, card.id, n)?;
+        }
+    }
+    writeln!(out, "}}")
+}
@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::{Almanac, Category, CategoryMap};
+
+// the whole range of categories we can map
+pub const EVERYTHING: Range<Category> = 0..Category::MAX;
+
+// split `range` into the bits `map` moves and the bits it leaves alone,
+// as (src, dst) pairs in order (dst == src where nothing moves)
+pub fn segments(map: &CategoryMap, range: Range<Category>) -> Vec<(Range<Category>, Range<Category>)> {
+    if range.is_empty() {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    for (src, dst) in map.overlapping(&range) {
+        // only the part of the segment inside our range
+        let start = src.start.max(range.start);
+        let end = src.end.min(range.end);
+        let dst_start = dst.start + (start - src.start);
+        out.push((start..end, dst_start..dst_start + (end - start)));
+    }
+    for gap in map.gaps(&range) {
+        out.push((gap.clone(), gap));
+    }
+    out.sort_by_key(|(src, _)| src.start);
+    out
+}
+
+// one map that does `first` and then `then`
+// - every piece of `first` (gaps too) gets split up by `then`
+// - pieces that end up where they started are left out, same as in the almanac
+pub fn compose(first: &CategoryMap, then: &CategoryMap) -> CategoryMap {
+    let mut map = CategoryMap::new();
+    for (src, dst) in segments(first, EVERYTHING) {
+        for (mid, out) in segments(then, dst.clone()) {
+            // where this bit of `mid` came from before `first` moved it
+            let start = src.start + (mid.start - dst.start);
+            if out.start != start {
+                map.insert(start..start + (mid.end - mid.start), out);
+            }
+        }
+    }
+    map
+}
+
+impl Almanac {
+    // fold a whole chain of maps down into one
+    pub fn compose_chain(chain: &[&CategoryMap]) -> CategoryMap {
+        chain
+            .iter()
+            .fold(CategoryMap::new(), |acc, map| compose(&acc, map))
+    }
+}
+
+// every piece of the map, including the parts it leaves alone
+pub fn write_table(map: &CategoryMap, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{:>25}  {:>25}  {:>12}", "src", "dst", "offset")?;
+    for (src, dst) in segments(map, EVERYTHING) {
+        let offset = dst.start as i128 - src.start as i128;
+        writeln!(
+            out,
+            "{:>25}  {:>25}  {:>+12}",
+            format!("{}..{}", src.start, src.end),
+            format!("{}..{}", dst.start, dst.end),
+            offset
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use crate::compose::{segments, EVERYTHING};
+use crate::{merge_ranges, Almanac, Category, CategoryMap};
+
+// a map turned around: where could a category have come from
+// - a value can have more than one source (something mapped onto it
+//   and itself, if it isn't mapped away) so we keep every piece
+#[derive(Debug)]
+pub struct InverseMap {
+    // (dst, src) sorted by dst
+    pieces: Vec<(Range<Category>, Range<Category>)>,
+}
+
+impl InverseMap {
+    pub fn new(map: &CategoryMap) -> Self {
+        let mut pieces: Vec<_> = segments(map, EVERYTHING)
+            .into_iter()
+            .map(|(src, dst)| (dst, src))
+            .collect();
+        pieces.sort_by_key(|(dst, _)| dst.start);
+        Self { pieces }
+    }
+
+    // every src range that lands somewhere in `range`
+    pub fn preimage(&self, range: Range<Category>, output: &mut Vec<Range<Category>>) {
+        for (dst, src) in &self.pieces {
+            if dst.start >= range.end {
+                break;
+            }
+            let start = dst.start.max(range.start);
+            let end = dst.end.min(range.end);
+            if start < end {
+                let src_start = src.start + (start - dst.start);
+                output.push(src_start..src_start + (end - start));
+            }
+        }
+    }
+}
+
+impl Almanac {
+    // the other way to `convert_range`, `range` is in the map's dst category
+    pub fn invert_range(range: Range<Category>, inverse: &InverseMap, output: &mut Vec<Range<Category>>) {
+        inverse.preimage(range, output);
+    }
+
+    // the other way to `convert_ranges`: everything in the start of the chain
+    // that ends up in `ranges`, merged and sorted
+    pub fn invert_ranges(ranges: Vec<Range<Category>>, chain: &[&CategoryMap]) -> Vec<Range<Category>> {
+        let mut ping = merge_ranges(ranges);
+        let mut pong = Vec::new();
+        for map in chain.iter().rev() {
+            let inverse = InverseMap::new(map);
+            for range in ping.drain(..) {
+                Almanac::invert_range(range, &inverse, &mut pong);
+            }
+            ping = merge_ranges(std::mem::take(&mut pong));
+        }
+        ping
+    }
+}
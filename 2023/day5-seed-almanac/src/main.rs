@@ -1,27 +1,119 @@
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::io::{BufReader, BufRead};
+use std::io::{BufReader, Read};
 use std::ops::Range;
 
 use clap::Parser;
 use rangemap::RangeMap;
 
+mod compose;
+mod inverse;
+mod report;
+mod svg;
+mod validate;
+mod verify;
+
+use validate::{Issue, MapBuilder};
+
 type Answer = u64;
 type PuzzleRepr = Almanac;
 
 type Category = u64;
 type CategoryMap = RangeMap<Category, Range<Category>>;
 
+// a map from one named category to another, e.g. seed-to-soil
+struct AlmanacMap {
+    src: String,
+    dst: String,
+    map: CategoryMap,
+}
+
 struct Almanac {
-    maps: Vec<CategoryMap>,
+    seeds: Vec<Category>,
+    // in the order they were read, use `path` to chain them up
+    maps: Vec<AlmanacMap>,
 }
 
 impl Almanac {
     fn new() -> Almanac {
         Self { 
+            seeds: Vec::new(),
             maps: Vec::new(),
         }
     }
 
+    // part 2 reads the seed line as (start, length) pairs
+    fn seed_ranges(&self) -> Vec<Range<Category>> {
+        self.seeds
+            .chunks(2)
+            // .inspect(|x| println!("{} {}", x[0], x[1]))
+            .map(|x| {
+                let start = x[0];
+                let len = x[1];
+                start..start+len
+            })
+            .collect()
+    }
+
+    // the maps that take a `from` category to a `to` category, in order
+    // - maps can be in any order and categories can branch,
+    //   we search breadth first so we get the shortest way there
+    fn path(&self, from: &str, to: &str) -> Option<Vec<&CategoryMap>> {
+        // category -> the map we took to get to it
+        let mut via: HashMap<&str, Option<usize>> = HashMap::from([(from, None)]);
+        let mut queue: VecDeque<&str> = VecDeque::from([from]);
+        while let Some(cat) = queue.pop_front() {
+            if cat == to {
+                break;
+            }
+            for (id, m) in self.maps.iter().enumerate() {
+                if m.src == cat && !via.contains_key(m.dst.as_str()) {
+                    via.insert(&m.dst, Some(id));
+                    queue.push_back(&m.dst);
+                }
+            }
+        }
+        // walk back from where we ended up
+        let mut path = Vec::new();
+        let mut cat = to;
+        while let Some(id) = *via.get(cat)? {
+            path.push(&self.maps[id].map);
+            cat = &self.maps[id].src;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    fn chain(&self, from: &str, to: &str) -> Vec<&CategoryMap> {
+        self.path(from, to)
+            .unwrap_or_else(|| panic!("no way to get from {} to {}", from, to))
+    }
+
+    // take a value through every map in the chain
+    fn convert(value: Category, chain: &[&CategoryMap]) -> Category {
+        chain
+            .iter()
+            .fold(value, |src, map| Almanac::convert_category(src, map))
+    }
+
+    // take ranges through every map in the chain
+    fn convert_ranges(ranges: Vec<Range<Category>>, chain: &[&CategoryMap]) -> Vec<Range<Category>> {
+        let mut ping: Vec<Range<Category>> = ranges;
+        let mut pong: Vec<Range<Category>> = Vec::new();
+        for map in chain {
+            for src in ping.drain(..) {
+                Almanac::convert_range(src, map, &mut pong);
+            }
+            std::mem::swap(&mut ping, &mut pong);
+        }
+        ping
+    }
+
+    // take seed ranges all the way through to location ranges
+    fn location_ranges(&self, seeds: Vec<Range<Category>>) -> Vec<Range<Category>> {
+        Almanac::convert_ranges(seeds, &self.chain("seed", "location"))
+    }
+
     fn convert_category(src: Category, map: &CategoryMap) -> Category {
         // attempt to see if the category has a conversion range
         if let Some((src_range, dst_range)) = map.get_key_value(&src) {
@@ -31,53 +123,45 @@ impl Almanac {
         }
     }
 
+    // convert a whole range at once, it can come out in pieces
+    // - walk every map segment the range overlaps (and the gaps between them)
+    // - overlapping bits move, gaps stay where they are
     fn convert_range(range: Range<Category>, map: &CategoryMap, output: &mut Vec<Range<Category>>) {
-        // attempt to see if the category has a conversion range
-        // we now have different cases here ...
-        // 1. ranges line up perfectly
-        // 2. range spills over n ranges
-        
         // println!("convert: {:?}", range);
-        if let Some((src_range, dst_range)) = map.get_key_value(&range.start) {
-            let (start, end) = if range.end > src_range.end {
-                // create new range
-                // convert that the same way
-                // println!("range spills over!! {:?} ({:?} -> {:?}))", range, src_range, dst_range);
-                let start = src_range.start.abs_diff(range.start) + dst_range.start;
-                let mid = src_range.end;            
-                let end = range.end;
-                Almanac::convert_range(mid..end, map, output);
-                (start, dst_range.end)
-            } else {
-                // println!("no spillage: {:?} ({:?} -> {:?}))", range, src_range, dst_range);
-                let start = src_range.start.abs_diff(range.start) + dst_range.start;            
-                let end = dst_range.end - src_range.end.abs_diff(range.end);
-                (start, end)
-            };
-            output.push(start..end);
-            // println!("res: {:?} ({:?})", start..end, dst_range);
-        } else {
-            // println!("value not in range: {}", range.start);
-            // check if the end of the range exists somewhere
-            if let Some((src_range, dst_range)) = map.get_key_value(&(range.end-1)) {
-                // println!("end is in range: {:?}", src_range);
-                let start = range.start;
-                let mid = src_range.start;
-                let end = src_range.start.abs_diff(range.end) + dst_range.start; // convert this
-                Almanac::convert_range(start..mid, map, output);
-                output.push(dst_range.start..end);
-            } else {
-                // panic!("value not in range: {:?}", range);
-                // println!("res: {:?}", range);
-                output.push(range);    
-            }
+        for (_, dst) in compose::segments(map, range) {
+            output.push(dst);
         }
     }
 }
 
+// sort ranges and join up the ones that touch or overlap
+fn merge_ranges(mut ranges: Vec<Range<Category>>) -> Vec<Range<Category>> {
+    ranges.retain(|r| !r.is_empty());
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<Category>> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+// N or N..M
+fn parse_range(s: &str) -> Result<Range<Category>, String> {
+    let num = |n: &str| n.trim().parse::<Category>().map_err(|e| format!("bad number '{}': {}", n, e));
+    match s.split_once("..") {
+        Some((start, end)) => Ok(num(start)?..num(end)?),
+        None => num(s).map(|n| n..n + 1),
+    }
+}
+
 struct Puzzle {
     input: BufReader<File>,
     repr: PuzzleRepr,
+    // problems found in the map lines while parsing
+    issues: Vec<Issue>,
 }
 
 impl Puzzle {
@@ -87,14 +171,64 @@ impl Puzzle {
         Self {
             input: reader,
             repr: PuzzleRepr::new(),
+            issues: Vec::new(),
         }
     }
 
     fn parse(&mut self) {
-        let mut string = String::new();
-        while self.input.read_line(&mut string).unwrap() > 0 {
-            // print!("{}", string);
-            string.clear();
+        let mut text = String::new();
+        self.input.read_to_string(&mut text).expect("failed to read input");
+
+        // the almanac is blocks of lines split up by blank ones
+        // - lines() takes care of \r\n and a missing newline at the end
+        // - keep the line numbers around for the issues
+        let mut blocks: Vec<Vec<(usize, &str)>> = Vec::new();
+        let mut block = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                if !block.is_empty() {
+                    blocks.push(std::mem::take(&mut block));
+                }
+            } else {
+                block.push((i + 1, line));
+            }
+        }
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+        let mut blocks = blocks.into_iter();
+
+        // first block is our seeds
+        let seeds = blocks.next().expect("no seeds");
+        self.repr.seeds = seeds
+            .iter()
+            .flat_map(|(_, line)| line.trim_start_matches("seeds:").split_whitespace())
+            .map(|s| s.parse().expect("failed to convert seed"))
+            .collect();
+
+        // then one block per map
+        for block in blocks {
+            let (_, header) = block[0];
+            // seed-to-soil map:
+            let (src, dst) = header
+                .trim_end_matches("map:")
+                .trim()
+                .split_once("-to-")
+                .expect("map header should be <src>-to-<dst> map:");
+            let mut builder = MapBuilder::new(src, dst);
+            for (line, range) in &block[1..] {
+                // parse a range
+                let range: Vec<Category> = range
+                    .split_whitespace()
+                    .map(|n| n.parse::<Category>().expect("failed to parse category"))
+                    .collect();
+                let [dst, src, len] = range[..] else {
+                    panic!("line {}: expected <dst> <src> <len>", line);
+                };
+                builder.add(*line, dst, src, len, &mut self.issues);
+            }
+            self.repr.maps.push(builder.finish());
         }
     }
 
@@ -111,58 +245,11 @@ impl Puzzle {
     // - map lists translation from src category to dst category
     // - describes ranges: dst, src, len
     // - numbers not in the map are 1-1
-    fn part1_solution(&mut self) -> Answer {
-        let mut string = String::new();
-        
-        // read the first line, that's our seeds
-        let _  = self.input.read_line(&mut string);
-        let mut items: Vec<Category> = string
-            .split(":")
-            .last()
-            .expect("no last")
-            .trim()
-            .split(" ")
-            .map(|s| s.parse().expect("failed to convert seed"))
-            .collect();
-        // skip newline
-        let _ = self.input.read_line(&mut string);
-        string.clear();
-
-        // a way to track the map that we are making
-        let mut map = CategoryMap::new();
-        let mut range: Vec<Category>  = Vec::with_capacity(3);
-        // now we parse the maps and convert along the way
-        while self.input.read_line(&mut string).unwrap() > 0 {
-            if string.contains("map") {
-                // println!("found map!!!");
-            }
-            // end map condition
-            else if string == "\n" {
-                // println!("end map!!!");
-                // convert the category we are on
-                for src in items.iter_mut() {
-                    *src = Almanac::convert_category(*src, &map);
-                }
-                map = CategoryMap::new();
-            } else {
-                // parse a range
-                range.extend(string
-                    .trim()
-                    .split(" ")
-                    .map(|n| n.parse::<Category>().expect("failed to parse category"))
-                );
-                let (dst, src, len) = (range[0], range[1], range[2]);
-                map.insert(src..src+len, dst..dst+len);
-                range.clear();
-            }
-            // print!("{}", string);
-            string.clear();
-        }
-
-        // print out final items (location)
-        // items.iter().for_each(|c| print!("{} ", *c));
-        // println!("");
-        *items.iter()
+    fn part1_solution(&self) -> Answer {
+        let chain = self.repr.chain("seed", "location");
+        self.repr.seeds
+            .iter()
+            .map(|seed| Almanac::convert(*seed, &chain))
             .min()
             .unwrap()
     }
@@ -171,96 +258,25 @@ impl Puzzle {
     // - the values come in pairs (start, length)
     // - now we consider all numbers in that range
     // - still find the nearest location
-    fn part2_solution(&mut self) -> Answer {
-        let mut string = String::new();
-        
-        // read the first line, that's our seeds
-        let _  = self.input.read_line(&mut string);
-        let mut ping_to_pong: bool = false;
-        let mut ping: Vec<Range<Category>> = Vec::new();
-        let mut pong: Vec<Range<Category>> = string
-            .split(":")
-            .last()
-            .expect("no last")
-            .trim()
-            .split(" ")
-            .map(|s| s.parse().expect("failed to convert seed"))
-            .collect::<Vec<u64>>()
-            .as_slice()
-            .chunks(2)
-            // .inspect(|x| println!("{} {}", x[0], x[1]))
-            .map(|x| {
-                let start = x[0];
-                let end = x[1];
-                start..start+end
-            })
-            .collect();
-
-        // skip newline
-        let _ = self.input.read_line(&mut string);
-        string.clear();
-
-        // a way to track the map that we are making
-        let mut map = CategoryMap::new();
-        let mut range: Vec<Category>  = Vec::with_capacity(3);
-        // now we parse the maps and convert along the way
-        while self.input.read_line(&mut string).unwrap() > 0 {
-            if string.contains("map") {
-                // println!("found map!!!");
-                // print!("{}", string);
-            }
-            // end map condition
-            else if string == "\n" {
-                // println!("end map!!!");
-                // convert the category we are on
-                if ping_to_pong {
-                    for src in ping.iter_mut() {
-                        Almanac::convert_range(src.clone(), &map, &mut pong);
-                    }
-                    ping.clear();
-                    ping_to_pong = false;
-                } else {
-                    for src in pong.iter_mut() {
-                        Almanac::convert_range(src.clone(), &map, &mut ping);
-                    }
-                    pong.clear();
-                    ping_to_pong = true;
-                }
-                // break;
-                map = CategoryMap::new();
-            } else {
-                // parse a range
-                range.extend(string
-                    .trim()
-                    .split(" ")
-                    .map(|n| n.parse::<Category>().expect("failed to parse category"))
-                );
-                let (dst, src, len) = (range[0], range[1], range[2]);
-                map.insert(src..src+len, dst..dst+len);
-                range.clear();
-            }
-            // print!("{}", string);
-            string.clear();
-        }
-
+    fn part2_solution(&self) -> Answer {
         // print out final items (location)
-        if !ping_to_pong {
-            // pong.iter().for_each(|c| print!("{:?} ", *c));
-            pong.iter()
-                .map(|r| r.clone().min().unwrap())
-                .min()
-                .unwrap()
-        } else {
-            // ping.iter().for_each(|c| print!("{:?} ", *c));
-            ping.iter()
-                .map(|r| r.clone().min().unwrap())
-                .min()
-                .unwrap()
-        }
+        self.repr.location_ranges(self.repr.seed_ranges())
+            .iter()
+            .filter(|r| !r.is_empty())
+            .map(|r| r.start)
+            .min()
+            .unwrap()
     }
 
+    #[allow(dead_code)] // debugging aid
     fn print(&self) {
-        todo!()
+        println!("seeds: {:?}", self.repr.seeds);
+        for m in self.repr.maps.iter() {
+            println!("{}-to-{} map:", m.src, m.dst);
+            for (src, dst) in m.map.iter() {
+                println!("  {:?} -> {:?}", src, dst);
+            }
+        }
     }
 }
 
@@ -274,40 +290,141 @@ struct Args {
     /// Run Part 2
     #[arg(short('s'), long)]
     run_part_2: bool,
+
+    /// Category the seed numbers are read as when converting
+    #[arg(long, default_value = "seed")]
+    from: String,
+
+    /// Convert the seed numbers to this category
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Find the --from values (seeds) that end up at this --to value (location), N or N..M
+    #[arg(short('i'), long, value_parser = parse_range)]
+    invert: Option<Range<Category>>,
+
+    /// Print the maps from --from to --to (or location) folded into one
+    #[arg(short('c'), long)]
+    compose: bool,
+
+    /// Print every location range the part 2 seed ranges end up in
+    #[arg(short('r'), long)]
+    ranges: bool,
+
+    /// Check part 2 by running every seed through the maps (slow!)
+    #[arg(long)]
+    verify_bruteforce: bool,
+
+    /// Plot every map, and --from to --to (or location) composed, as SVG files in this directory
+    #[arg(long)]
+    svg: Option<std::path::PathBuf>,
+
+    /// Shade the seed ranges on the plots as they pass through each map
+    #[arg(long, requires = "svg")]
+    svg_seeds: bool,
+
+    /// Refuse to run if any map lines overlap, repeat, are empty or overflow
+    #[arg(long)]
+    strict: bool,
 }
 
 fn main() {
     // clap args parsing
     let args = Args::parse();
+    let mut puzzle = Puzzle::new(INPUT);
+    puzzle.parse();
+    for issue in &puzzle.issues {
+        eprintln!("warning: {}", issue);
+    }
+    if args.strict && !puzzle.issues.is_empty() {
+        eprintln!("rejecting {}: {} bad map lines", INPUT, puzzle.issues.len());
+        std::process::exit(1);
+    }
     if args.run_part_1 {
-        let ans = part1();
+        let ans = puzzle.part1_solution();
         println!("part 1: {}", ans);
     }
     if args.run_part_2 {
-        let ans = part2();
+        let ans = puzzle.part2_solution();
         println!("part 2: {}", ans);
     }
+    if args.verify_bruteforce {
+        let ans = puzzle.part2_solution();
+        let brute = puzzle.repr.bruteforce(true).expect("no seeds");
+        println!("brute force: {}", brute);
+        assert_eq!(ans, brute, "range answer doesn't match brute force");
+    }
+    if args.ranges {
+        report::write_report(&puzzle.repr.location_report(), &mut std::io::stdout().lock())
+            .expect("failed to write report");
+    }
+    if let Some(to) = &args.to {
+        let almanac = &puzzle.repr;
+        match almanac.path(&args.from, to) {
+            Some(chain) => {
+                for seed in &almanac.seeds {
+                    println!("{} {} -> {} {}", args.from, seed, to, Almanac::convert(*seed, &chain));
+                }
+            }
+            None => eprintln!("no way to get from {} to {}", args.from, to),
+        }
+    }
+    if let Some(range) = &args.invert {
+        let to = args.to.as_deref().unwrap_or("location");
+        match puzzle.repr.path(&args.from, to) {
+            Some(chain) => {
+                println!("{} {}..{} comes from:", to, range.start, range.end);
+                for src in Almanac::invert_ranges(vec![range.clone()], &chain) {
+                    println!("  {} {}..{}", args.from, src.start, src.end);
+                }
+            }
+            None => eprintln!("no way to get from {} to {}", args.from, to),
+        }
+    }
+    if let Some(dir) = &args.svg {
+        let to = args.to.as_deref().unwrap_or("location");
+        std::fs::create_dir_all(dir).expect("failed to create svg directory");
+        let almanac = &puzzle.repr;
+        let seeds = almanac.seed_ranges();
+        // the seed ranges as they look when they reach `category`
+        let overlay = |category: &str| match (args.svg_seeds, almanac.path(&args.from, category)) {
+            (true, Some(chain)) => merge_ranges(Almanac::convert_ranges(seeds.clone(), &chain)),
+            _ => Vec::new(),
+        };
+        let mut plots: Vec<(&str, &str, CategoryMap)> = almanac.maps
+            .iter()
+            .map(|m| (m.src.as_str(), m.dst.as_str(), m.map.clone()))
+            .collect();
+        match almanac.path(&args.from, to) {
+            Some(chain) => plots.push((&args.from, to, Almanac::compose_chain(&chain))),
+            None => eprintln!("no way to get from {} to {}", args.from, to),
+        }
+        for (src, dst, map) in plots {
+            let path = dir.join(format!("{}-to-{}.svg", src, dst));
+            let mut file = std::io::BufWriter::new(File::create(&path).expect("failed to create svg"));
+            svg::write_svg(&map, (src, dst), &overlay(src), &mut file).expect("failed to write svg");
+            println!("wrote {}", path.display());
+        }
+    }
+    if args.compose {
+        let to = args.to.as_deref().unwrap_or("location");
+        match puzzle.repr.path(&args.from, to) {
+            Some(chain) => {
+                println!("{}-to-{}:", args.from, to);
+                compose::write_table(&Almanac::compose_chain(&chain), &mut std::io::stdout().lock())
+                    .expect("failed to write map");
+            }
+            None => eprintln!("no way to get from {} to {}", args.from, to),
+        }
+    }
 }
 
 const INPUT: &str = "input.txt";
 
-fn part1() -> Answer {
-    let mut puzzle = Puzzle::new(INPUT);
-    // puzzle.parse();
-    let result = puzzle.part1_solution();
-    result
-}
-
-fn part2() -> Answer {
-    let mut puzzle = Puzzle::new(INPUT);
-    // puzzle.parse();
-    let result = puzzle.part2_solution();
-    result
-}
-
 #[cfg(test)]
 mod test {
     use crate::*;
+    use proptest::prelude::*;
 
     const TEST: &str = "test.txt";
 
@@ -315,7 +432,7 @@ mod test {
     fn test_part1() {
         const RESULT: u64 = 35;
         let mut puzzle = Puzzle::new(TEST);
-        // puzzle.parse();
+        puzzle.parse();
         // puzzle.print();
         let result = puzzle.part1_solution();
         assert_eq!(result, RESULT);
@@ -325,9 +442,342 @@ mod test {
     fn test_part2() {
         const RESULT: u64 = 46;
         let mut puzzle = Puzzle::new(TEST);
-        // puzzle.parse();
+        puzzle.parse();
         // puzzle.print();
         let result = puzzle.part2_solution();
         assert_eq!(result, RESULT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse() {
+        let mut puzzle = Puzzle::new(TEST);
+        puzzle.parse();
+        let almanac = &puzzle.repr;
+        assert_eq!(almanac.seeds, vec![79, 14, 55, 13]);
+        assert_eq!(almanac.seed_ranges(), vec![79..93, 55..68]);
+        assert_eq!(almanac.maps.len(), 7);
+        // seed-to-soil
+        assert_eq!((almanac.maps[0].src.as_str(), almanac.maps[0].dst.as_str()), ("seed", "soil"));
+        let soil: Vec<_> = almanac.maps[0].map.iter().map(|(s, d)| (s.clone(), d.clone())).collect();
+        assert_eq!(soil, vec![(50..98, 52..100), (98..100, 50..52)]);
+        let locations = locations(almanac);
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+    }
+
+    fn locations(almanac: &Almanac) -> Vec<Category> {
+        let chain = almanac.chain("seed", "location");
+        almanac.seeds.iter().map(|s| Almanac::convert(*s, &chain)).collect()
+    }
+
+    fn parsed(input: &str) -> Almanac {
+        let mut puzzle = Puzzle::new(input);
+        puzzle.parse();
+        puzzle.repr
+    }
+
+    #[test]
+    fn test_path() {
+        let almanac = parsed(TEST);
+        let humidity: Vec<Category> = almanac.seeds
+            .iter()
+            .map(|s| Almanac::convert(*s, &almanac.chain("seed", "humidity")))
+            .collect();
+        assert_eq!(humidity, vec![78, 43, 82, 35]);
+        // soil 81 is water 81
+        assert_eq!(Almanac::convert(81, &almanac.chain("soil", "water")), 81);
+        assert_eq!(almanac.chain("seed", "seed").len(), 0);
+        // maps only go one way
+        assert!(almanac.path("location", "seed").is_none());
+        assert!(almanac.path("seed", "gem").is_none());
+    }
+
+    #[test]
+    fn test_path_reordered() {
+        // the test maps back to front
+        let almanac = parsed("fixtures/reordered.txt");
+        assert_eq!(almanac.maps[0].src, "humidity");
+        let locations = locations(&almanac);
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+        let ranges = almanac.location_ranges(almanac.seed_ranges());
+        assert_eq!(ranges.iter().map(|r| r.start).min(), Some(46));
+    }
+
+    #[test]
+    fn test_path_branches() {
+        // soil also goes off to mineral -> gem, and water has a dead end
+        let almanac = parsed("fixtures/branches.txt");
+        let locations = locations(&almanac);
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+        let chain = almanac.chain("seed", "gem");
+        assert_eq!(chain.len(), 3);
+        // seed 79 -> soil 81 -> mineral 1081 -> gem 2081
+        assert_eq!(Almanac::convert(79, &chain), 2081);
+    }
+
+    #[test]
+    fn test_svg() {
+        // 10..20 -> 100..110, left alone either side of it
+        let mut out = Vec::new();
+        let seeds = 5..15;
+        svg::write_svg(&map(&[(100, 10, 10)]), ("a", "b"), &[seeds], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches(r#"<line class="mapped""#).count(), 1);
+        assert_eq!(out.matches(r#"<line class="identity""#).count(), 2);
+        assert_eq!(out.matches(r#"<rect class="seeds""#).count(), 1);
+        assert!(out.contains(">a-to-b</text>"));
+        assert!(out.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_bruteforce() {
+        for input in [TEST, "fixtures/reordered.txt", "fixtures/branches.txt"] {
+            let mut puzzle = Puzzle::new(input);
+            puzzle.parse();
+            assert_eq!(puzzle.repr.bruteforce(false), Some(puzzle.part2_solution()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_location_report() {
+        let report = parsed(TEST).location_report();
+        assert_eq!(report.ranges, vec![
+            (46..61, 15),
+            (82..85, 3),
+            (86..90, 4),
+            (94..99, 5),
+        ]);
+        assert_eq!(report.seeds, 27);
+        assert_eq!(report.covered, 27);
+        assert_eq!(report.min, Some((46, 79..93)));
+    }
+
+    #[test]
+    fn test_parse_variants() {
+        // every one is test.txt with the layout messed with
+        for input in [
+            "fixtures/no-trailing-newline.txt",
+            "fixtures/crlf.txt",
+            "fixtures/extra-blank-lines.txt",
+            "fixtures/whitespace.txt",
+        ] {
+            let mut puzzle = Puzzle::new(input);
+            puzzle.parse();
+            assert!(puzzle.issues.is_empty(), "{}", input);
+            assert_eq!(puzzle.repr.maps.len(), 7, "{}", input);
+            assert_eq!(locations(&puzzle.repr), vec![82, 43, 86, 35], "{}", input);
+            assert_eq!(puzzle.part2_solution(), 46, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        use validate::IssueKind::*;
+        let mut puzzle = Puzzle::new("fixtures/malformed.txt");
+        puzzle.parse();
+        let found: Vec<_> = puzzle.issues.iter().map(|i| (i.line, i.map.as_str(), &i.kind)).collect();
+        assert_eq!(found, vec![
+            (6, "seed-to-soil", &Duplicate { line: 5 }),
+            (7, "seed-to-soil", &Overlap { line: 4 }),
+            (7, "seed-to-soil", &Overlap { line: 5 }),
+            (8, "seed-to-soil", &ZeroLength),
+            (11, "soil-to-location", &Overflow),
+        ]);
+        assert_eq!(puzzle.issues[1].to_string(), "line 7 (seed-to-soil): source overlaps line 4");
+        // the earlier lines win, line 7 only gets to map 100..105
+        let soil = &puzzle.repr.maps[0].map;
+        assert_eq!(Almanac::convert_category(95, soil), 97);
+        assert_eq!(Almanac::convert_category(99, soil), 51);
+        assert_eq!(Almanac::convert_category(100, soil), 70);
+        assert_eq!(Almanac::convert_category(105, soil), 105);
+        // and the real input is clean
+        let mut puzzle = Puzzle::new(TEST);
+        puzzle.parse();
+        assert!(puzzle.issues.is_empty());
+    }
+
+    fn map(lines: &[(Category, Category, Category)]) -> CategoryMap {
+        let mut map = CategoryMap::new();
+        for (dst, src, len) in lines {
+            map.insert(*src..src + len, *dst..dst + len);
+        }
+        map
+    }
+
+    #[test]
+    fn test_compose() {
+        // 10..20 -> 100..110, then 105..115 -> 0..10
+        let first = map(&[(100, 10, 10)]);
+        let then = map(&[(0, 105, 10)]);
+        let both = compose::compose(&first, &then);
+        let pieces: Vec<_> = both.iter().map(|(s, d)| (s.clone(), d.clone())).collect();
+        assert_eq!(pieces, vec![
+            // only the first map moves these
+            (10..15, 100..105),
+            // both maps move these
+            (15..20, 0..5),
+            // only the second map moves these (the first leaves them be)
+            (105..115, 0..10),
+        ]);
+        for x in 0..200 {
+            let expected = Almanac::convert_category(Almanac::convert_category(x, &first), &then);
+            assert_eq!(Almanac::convert_category(x, &both), expected, "{}", x);
+        }
+    }
+
+    #[test]
+    fn test_compose_chain() {
+        let almanac = parsed(TEST);
+        let chain = almanac.chain("seed", "location");
+        let seed_to_location = Almanac::compose_chain(&chain);
+        for x in 0..200 {
+            assert_eq!(
+                Almanac::convert_category(x, &seed_to_location),
+                Almanac::convert(x, &chain),
+                "{}", x
+            );
+        }
+        assert_eq!(Almanac::compose_chain(&[]), CategoryMap::new());
+
+        // the real seeds land in the same place too
+        let almanac = parsed("input.txt");
+        let chain = almanac.chain("seed", "location");
+        let composed = Almanac::compose_chain(&chain);
+        for seed in &almanac.seeds {
+            assert_eq!(Almanac::convert_category(*seed, &composed), Almanac::convert(*seed, &chain));
+        }
+
+        let mut out = Vec::new();
+        compose::write_table(&seed_to_location, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[1].split_whitespace().collect::<Vec<_>>(), vec!["0..14", "22..36", "+22"]);
+        assert_eq!(lines.last().unwrap().split_whitespace().next(), Some("100..18446744073709551615"));
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(merge_ranges(vec![5..8, 0..2, 1..3, 8..9, 4..4, 12..20]), vec![0..3, 5..9, 12..20]);
+        assert_eq!(merge_ranges(vec![]), vec![]);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("46"), Ok(46..47));
+        assert_eq!(parse_range("10..20"), Ok(10..20));
+        assert!(parse_range("ten").is_err());
+        assert!(parse_range("1..x").is_err());
+    }
+
+    #[test]
+    fn test_inverse_map() {
+        // 10..20 -> 100..110, so 105 comes from 15 and 105 itself
+        let m = map(&[(100, 10, 10)]);
+        let inverse = inverse::InverseMap::new(&m);
+        let mut out = Vec::new();
+        inverse.preimage(105..106, &mut out);
+        assert_eq!(merge_ranges(out), vec![15..16, 105..106]);
+        // and 15 is mapped away, nothing lands there
+        let mut out = Vec::new();
+        inverse.preimage(15..16, &mut out);
+        assert_eq!(out, vec![]);
+    }
+
+    #[test]
+    fn test_invert_round_trip() {
+        let almanac = parsed(TEST);
+        let chain = almanac.chain("seed", "location");
+        // every seed is one of the seeds for its location
+        for seed in &almanac.seeds {
+            let location = Almanac::convert(*seed, &chain);
+            let spot = location..location + 1;
+            let seeds = Almanac::invert_ranges(vec![spot], &chain);
+            assert!(seeds.iter().any(|r| r.contains(seed)), "{} -> {}", seed, location);
+        }
+        // part 2's lowest location comes from seed 82
+        let lowest = 46..47;
+        let seeds = Almanac::invert_ranges(vec![lowest], &chain);
+        assert!(seeds.iter().any(|r| r.contains(&82)));
+
+        // and going back is exact: the seeds below 200 that land in a location
+        // range are just the ones the inverse gives us
+        for location in [0..10, 35..47, 46..47, 60..100] {
+            let seeds = Almanac::invert_ranges(vec![location.clone()], &chain);
+            let expected: Vec<Category> = (0..200)
+                .filter(|s| location.contains(&Almanac::convert(*s, &chain)))
+                .collect();
+            let found: Vec<Category> = seeds
+                .iter()
+                .flat_map(|r| r.start.min(200)..r.end.min(200))
+                .collect();
+            assert_eq!(found, expected, "{:?}", location);
+        }
+    }
+
+    #[test]
+    fn test_convert_range() {
+        // 10..20 -> 100..110, 30..40 -> 0..10
+        let m = map(&[(100, 10, 10), (0, 30, 10)]);
+        let convert = |range: Range<Category>| {
+            let mut out = Vec::new();
+            Almanac::convert_range(range, &m, &mut out);
+            out
+        };
+        // starts and ends in gaps but covers both segments
+        assert_eq!(convert(5..45), vec![5..10, 100..110, 20..30, 0..10, 40..45]);
+        // inside one segment
+        assert_eq!(convert(12..15), vec![102..105]);
+        // spills out both sides
+        assert_eq!(convert(8..22), vec![8..10, 100..110, 20..22]);
+        // nothing mapped
+        assert_eq!(convert(50..60), vec![50..60]);
+        assert_eq!(convert(50..50), vec![]);
+    }
+
+    // a random map with sources that don't overlap, as (dst, src, len) lines
+    fn random_map() -> impl Strategy<Value = CategoryMap> {
+        prop::collection::vec((0u64..20, 1u64..20, 0u64..200), 0..6).prop_map(|lines| {
+            let mut m = CategoryMap::new();
+            let mut src = 0;
+            for (gap, len, dst) in lines {
+                src += gap;
+                m.insert(src..src + len, dst..dst + len);
+                src += len;
+            }
+            m
+        })
+    }
+
+    proptest! {
+        // converting a range has to give the same values as converting
+        // every number in it one by one
+        #[test]
+        fn prop_convert_range(m in random_map(), start in 0u64..150, len in 0u64..60) {
+            let mut out = Vec::new();
+            Almanac::convert_range(start..start + len, &m, &mut out);
+            let mut by_range: Vec<Category> = out.into_iter().flatten().collect();
+            let mut by_value: Vec<Category> = (start..start + len)
+                .map(|x| Almanac::convert_category(x, &m))
+                .collect();
+            by_range.sort();
+            by_value.sort();
+            prop_assert_eq!(by_range, by_value);
+        }
+
+        // same again through a chain of maps
+        #[test]
+        fn prop_convert_ranges(maps in prop::collection::vec(random_map(), 1..4), start in 0u64..150, len in 0u64..60) {
+            let chain: Vec<&CategoryMap> = maps.iter().collect();
+            let seeds = start..start + len;
+            let mut by_range: Vec<Category> = Almanac::convert_ranges(vec![seeds], &chain)
+                .into_iter()
+                .flatten()
+                .collect();
+            let mut by_value: Vec<Category> = (start..start + len)
+                .map(|x| Almanac::convert(x, &chain))
+                .collect();
+            by_range.sort();
+            by_value.sort();
+            prop_assert_eq!(by_range, by_value);
+        }
+    }
+}
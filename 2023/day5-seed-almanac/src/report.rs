@@ -0,0 +1,62 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::{merge_ranges, Almanac, Category};
+
+// everywhere the part 2 seed ranges end up
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocationReport {
+    // merged and sorted, with how many seeds land in each
+    // - a location can be hit by more than one seed so the
+    //   count can be bigger than the range
+    pub ranges: Vec<(Range<Category>, u64)>,
+    pub seeds: u64,
+    // distinct locations hit
+    pub covered: u64,
+    // lowest location and the seed range it came from
+    pub min: Option<(Category, Range<Category>)>,
+}
+
+impl Almanac {
+    pub fn location_report(&self) -> LocationReport {
+        // convert the seed ranges one at a time so we know who went where
+        let pieces: Vec<(Range<Category>, Range<Category>)> = self.seed_ranges()
+            .into_iter()
+            .flat_map(|seeds| {
+                self.location_ranges(vec![seeds.clone()])
+                    .into_iter()
+                    .filter(|r| !r.is_empty())
+                    .map(move |r| (seeds.clone(), r))
+            })
+            .collect();
+
+        let merged = merge_ranges(pieces.iter().map(|(_, r)| r.clone()).collect());
+        let mut ranges: Vec<_> = merged.into_iter().map(|r| (r, 0)).collect();
+        for (_, piece) in &pieces {
+            // a piece sits inside exactly one merged range
+            let i = ranges.partition_point(|(r, _)| r.end <= piece.start);
+            ranges[i].1 += piece.end - piece.start;
+        }
+
+        LocationReport {
+            seeds: self.seed_ranges().iter().map(|r| r.end - r.start).sum(),
+            covered: ranges.iter().map(|(r, _)| r.end - r.start).sum(),
+            min: pieces
+                .into_iter()
+                .min_by_key(|(_, r)| r.start)
+                .map(|(seeds, r)| (r.start, seeds)),
+            ranges,
+        }
+    }
+}
+
+pub fn write_report(report: &LocationReport, out: &mut impl Write) -> io::Result<()> {
+    for (r, seeds) in &report.ranges {
+        writeln!(out, "{:>12}..{:<12} {:>12} seeds", r.start, r.end, seeds)?;
+    }
+    writeln!(out, "{} seeds over {} locations in {} ranges", report.seeds, report.covered, report.ranges.len())?;
+    if let Some((min, seeds)) = &report.min {
+        writeln!(out, "lowest location {} from seeds {}..{}", min, seeds.start, seeds.end)?;
+    }
+    Ok(())
+}
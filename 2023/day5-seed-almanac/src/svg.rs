@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::compose::segments;
+use crate::{Category, CategoryMap};
+
+const SIZE: f64 = 600.0;
+const MARGIN: f64 = 50.0;
+
+const STYLE: &str = "\
+.axis { stroke: black; }
+.mapped { stroke: steelblue; stroke-width: 2; }
+.identity { stroke: grey; stroke-width: 1; stroke-dasharray: 4 4; }
+.seeds { fill: orange; fill-opacity: 0.25; }
+text { font: 12px sans-serif; }";
+
+// draw a map as a function src -> dst
+// - moved pieces are solid, the bits it leaves alone are dashed
+// - `overlay` ranges (in src) get shaded so you can see what goes through
+pub fn write_svg(
+    map: &CategoryMap,
+    (src, dst): (&str, &str),
+    overlay: &[Range<Category>],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // zoom to what's actually mapped, everything past it is just more identity
+    let extent = map
+        .iter()
+        .flat_map(|(s, d)| [s.end, d.end])
+        .chain(overlay.iter().map(|r| r.end))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let inner = SIZE - 2.0 * MARGIN;
+    let x = |v: Category| MARGIN + v as f64 / extent as f64 * inner;
+    let y = |v: Category| SIZE - MARGIN - v as f64 / extent as f64 * inner;
+
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}">"#)?;
+    writeln!(out, "<style>\n{}\n</style>", STYLE)?;
+    writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    writeln!(out, r#"<text x="{}" y="20" text-anchor="middle">{}-to-{}</text>"#, SIZE / 2.0, src, dst)?;
+
+    for r in overlay {
+        writeln!(
+            out,
+            r#"<rect class="seeds" x="{:.2}" y="{MARGIN}" width="{:.2}" height="{inner}"/>"#,
+            x(r.start),
+            x(r.end) - x(r.start)
+        )?;
+    }
+
+    // axes, with the ends labelled
+    writeln!(out, r#"<line class="axis" x1="{MARGIN}" y1="{0}" x2="{0}" y2="{0}"/>"#, SIZE - MARGIN)?;
+    writeln!(out, r#"<line class="axis" x1="{MARGIN}" y1="{MARGIN}" x2="{MARGIN}" y2="{}"/>"#, SIZE - MARGIN)?;
+    writeln!(out, r#"<text x="{MARGIN}" y="{}" text-anchor="middle">0</text>"#, SIZE - MARGIN + 15.0)?;
+    writeln!(out, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, SIZE - MARGIN, SIZE - MARGIN + 15.0, extent)?;
+    writeln!(out, r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#, SIZE / 2.0, SIZE - 10.0, src)?;
+    writeln!(out, r#"<text x="15" y="{}" transform="rotate(-90 15 {0})" text-anchor="middle">{}</text>"#, SIZE / 2.0, dst)?;
+    writeln!(out, r#"<text x="{}" y="{MARGIN}" text-anchor="end">{}</text>"#, MARGIN - 5.0, extent)?;
+
+    for (s, d) in segments(map, 0..extent) {
+        let class = if s == d { "identity" } else { "mapped" };
+        writeln!(
+            out,
+            r#"<line class="{}" x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}"/>"#,
+            class,
+            x(s.start),
+            y(d.start),
+            x(s.end),
+            y(d.end)
+        )?;
+    }
+    writeln!(out, "</svg>")
+}
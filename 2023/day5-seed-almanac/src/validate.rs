@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use rangemap::RangeMap;
+
+use crate::{AlmanacMap, Category, CategoryMap};
+
+// something off about a `dst src len` line
+#[derive(Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    // the exact same line was already in this map
+    Duplicate { line: usize },
+    // part of the source range is already mapped by an earlier line
+    Overlap { line: usize },
+    ZeroLength,
+    // src+len or dst+len doesn't fit in a u64
+    Overflow,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub line: usize,
+    // which map it was in, e.g. seed-to-soil
+    pub map: String,
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} ({}): ", self.line, self.map)?;
+        match self.kind {
+            IssueKind::Duplicate { line } => write!(f, "duplicate of line {}", line),
+            IssueKind::Overlap { line } => write!(f, "source overlaps line {}", line),
+            IssueKind::ZeroLength => write!(f, "zero length range"),
+            IssueKind::Overflow => write!(f, "range runs past {}", Category::MAX),
+        }
+    }
+}
+
+// builds up one map, checking each line as it goes in
+// - bad lines are reported and only the parts that are safe get inserted:
+//   duplicates, zero lengths and overflows are dropped, overlaps only
+//   fill in what earlier lines left unmapped (first line wins)
+pub struct MapBuilder {
+    src: String,
+    dst: String,
+    map: CategoryMap,
+    // which line mapped each source value
+    owner: RangeMap<Category, usize>,
+    seen: HashMap<(Category, Category, Category), usize>,
+}
+
+impl MapBuilder {
+    pub fn new(src: &str, dst: &str) -> Self {
+        Self {
+            src: src.to_string(),
+            dst: dst.to_string(),
+            map: CategoryMap::new(),
+            owner: RangeMap::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, line: usize, dst: Category, src: Category, len: Category, issues: &mut Vec<Issue>) {
+        let mut report = |kind| issues.push(Issue {
+            line,
+            map: format!("{}-to-{}", self.src, self.dst),
+            kind,
+        });
+        if let Some(first) = self.seen.get(&(dst, src, len)) {
+            report(IssueKind::Duplicate { line: *first });
+            return;
+        }
+        self.seen.insert((dst, src, len), line);
+        if len == 0 {
+            report(IssueKind::ZeroLength);
+            return;
+        }
+        let (Some(src_end), Some(_)) = (src.checked_add(len), dst.checked_add(len)) else {
+            report(IssueKind::Overflow);
+            return;
+        };
+
+        // report each earlier line we run into once
+        let mut earlier: Vec<usize> = self.owner
+            .overlapping(&(src..src_end))
+            .map(|(_, l)| *l)
+            .collect();
+        earlier.sort();
+        earlier.dedup();
+        for l in earlier {
+            report(IssueKind::Overlap { line: l });
+        }
+
+        let gaps: Vec<_> = self.owner.gaps(&(src..src_end)).collect();
+        for gap in gaps {
+            let start = dst + (gap.start - src);
+            let end = dst + (gap.end - src);
+            self.map.insert(gap.clone(), start..end);
+            self.owner.insert(gap, line);
+        }
+    }
+
+    pub fn finish(self) -> AlmanacMap {
+        AlmanacMap { src: self.src, dst: self.dst, map: self.map }
+    }
+}
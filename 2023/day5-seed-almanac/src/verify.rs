@@ -0,0 +1,48 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rayon::prelude::*;
+
+use crate::{Almanac, Category};
+
+// seeds handed to a thread at a time
+const CHUNK: Category = 1 << 20;
+
+impl Almanac {
+    // part 2 the slow way: every single seed through every map
+    // - split the seed ranges into chunks and spread them over the cores
+    // - prints how far along it is to stderr when `progress` is set
+    pub fn bruteforce(&self, progress: bool) -> Option<Category> {
+        let chain = self.chain("seed", "location");
+        let chunks: Vec<Range<Category>> = self.seed_ranges()
+            .into_iter()
+            .flat_map(|r| {
+                (r.start..r.end)
+                    .step_by(CHUNK as usize)
+                    .map(move |start| start..r.end.min(start + CHUNK))
+            })
+            .collect();
+        let total: Category = chunks.iter().map(|c| c.end - c.start).sum();
+        let done = AtomicU64::new(0);
+
+        let min = chunks
+            .into_par_iter()
+            .filter_map(|chunk| {
+                let len = chunk.end - chunk.start;
+                let min = chunk
+                    .map(|seed| chain.iter().fold(seed, |v, map| Almanac::convert_category(v, map)))
+                    .min();
+                let before = done.fetch_add(len, Ordering::Relaxed);
+                // only the chunk that crosses a percent says anything
+                if progress && before * 100 / total != (before + len) * 100 / total {
+                    eprint!("\rverifying: {}%", (before + len) * 100 / total);
+                }
+                min
+            })
+            .min();
+        if progress {
+            eprintln!();
+        }
+        min
+    }
+}